@@ -1,12 +1,12 @@
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     sync::{Arc, LazyLock},
 };
 
 use az::SaturatingAs;
 use covey::{
     ActivationTarget,
-    covey_schema::{hotkey::Hotkey, style::UserStyle},
+    covey_schema::{hotkey::Hotkey, id::CommandId, style::UserStyle},
 };
 use eframe::CreationContext;
 use egui::{
@@ -16,6 +16,8 @@ use egui::{
 };
 
 use crate::{
+    preview::PreviewPane,
+    prompt::{PromptForm, PromptState},
     row::ListCell,
     widgets::{Container, ImageIcon},
 };
@@ -23,6 +25,8 @@ use crate::{
 pub mod cli;
 mod conv;
 mod hotkeys;
+mod preview;
+mod prompt;
 mod row;
 mod style;
 pub mod widgets;
@@ -42,8 +46,19 @@ pub struct App {
     pub cli: cli::Receiver,
     pub plugin_actions: covey::ActionReceiver,
     input: String,
+    /// Whether a query has been sent since the app was last opened.
+    queried_since_open: bool,
     list: Option<covey::List>,
     list_selection: usize,
+    /// Indices of list items that commands are activated on instead of the
+    /// selected item.
+    ///
+    /// Items with a key stay marked when the list is updated.
+    marked: BTreeSet<usize>,
+    /// Question from a plugin that is shown instead of the list.
+    prompt: Option<PromptForm>,
+    /// Running commands that are reporting their progress, oldest first.
+    tasks: Vec<(covey::Task, covey::Progress)>,
     /// Whether the last opening of the app has been focused.
     ///
     /// Used to avoid closing the app early if focus isn't gained for a bit.
@@ -79,8 +94,12 @@ impl App {
             host,
             plugin_actions: actions,
             input: String::new(),
+            queried_since_open: false,
             list: None,
             list_selection: 0,
+            marked: BTreeSet::new(),
+            prompt: None,
+            tasks: Vec::new(),
             app_has_been_focused: false,
             gui_settings,
             is_closed: true,
@@ -112,6 +131,8 @@ impl App {
         };
 
         self.is_closed = false;
+        self.queried_since_open = false;
+        self.host.window_shown();
         let result = eframe::run_native(
             "covey",
             options.clone(),
@@ -123,6 +144,7 @@ impl App {
         );
         self.app_has_been_focused = false;
         self.is_closed = true;
+        self.host.window_hidden();
         result
     }
 
@@ -234,15 +256,25 @@ impl App {
                 AppControlFlow::CloseGui | AppControlFlow::ExitProcess => return,
             }
         }
-        self.handle_keyboard_input(ui, &mut rendering_state);
 
         // The UI
-        self.show_input(ui, &rendering_state);
-        ui.add_space(self.style().main_component_gap());
         ui.style_mut().interaction.selectable_labels = false;
-        self.show_list(ui, &rendering_state);
-        ui.add_space(self.style().main_component_gap());
-        self.show_buttom_bar(ui);
+        if let Some(prompt) = &mut self.prompt {
+            let style = &self.host.config().style;
+            if let PromptState::Closed(answer) = prompt.show(ui, style) {
+                self.host.answer_prompt(prompt.prompt(), answer);
+                self.prompt = None;
+            }
+        } else {
+            self.handle_keyboard_input(ui, &mut rendering_state);
+
+            self.show_breadcrumbs(ui);
+            self.show_input(ui, &rendering_state);
+            ui.add_space(self.style().main_component_gap());
+            self.show_list(ui, &rendering_state);
+            ui.add_space(self.style().main_component_gap());
+            self.show_buttom_bar(ui);
+        }
 
         // set window size //
         let existing_height = ui.content_rect().height();
@@ -314,59 +346,142 @@ impl App {
                 self.is_closed = true;
                 AppControlFlow::CloseGui
             }
-            covey::Action::Copy(str) => {
-                if let Some(ui) = ui {
-                    ui.copy_text(str);
-                } else {
-                    _ = arboard::Clipboard::new()
-                        .and_then(|mut c| c.set_text(str))
-                        .inspect_err(|e| tracing::error!("failed to set clipboard text: {e:#}"));
-                }
+            covey::Action::Copy(covey::ClipboardContent::Text(str)) if let Some(ui) = ui => {
+                ui.copy_text(str);
                 AppControlFlow::Continue
             }
-            covey::Action::DisplayError(title, desc) => {
-                tokio::spawn(async move {
-                    _ = notify_rust::Notification::new()
-                        .summary(&title)
-                        .body(&desc)
-                        .show_async()
-                        .await
-                        .inspect_err(|e| {
-                            tracing::error!("failed to display error notification: {e:#}");
-                            tracing::error!("notification was:\n{title}\n{desc}");
-                        });
+            covey::Action::Copy(content) => {
+                _ = set_clipboard(content)
+                    .inspect_err(|e| tracing::error!("failed to set clipboard: {e:#}"));
+                AppControlFlow::Continue
+            }
+            covey::Action::DisplayError(title, body) => {
+                show_notification(covey::Notification {
+                    severity: covey::Severity::Error,
+                    title,
+                    body,
+                    timeout: None,
                 });
                 AppControlFlow::Continue
             }
+            covey::Action::Notify(notification) => {
+                show_notification(notification);
+                AppControlFlow::Continue
+            }
+            covey::Action::Progress(task, progress) => {
+                match self.tasks.iter_mut().find(|(t, _)| *t == task) {
+                    Some((_, old_progress)) => *old_progress = progress,
+                    None => self.tasks.push((task, progress)),
+                }
+                AppControlFlow::Continue
+            }
+            covey::Action::EndProgress(task) => {
+                self.tasks.retain(|(t, _)| *t != task);
+                AppControlFlow::Continue
+            }
+            covey::Action::PushView(view) => {
+                tracing::debug!("pushed view {:?}", view.title());
+                self.input.clear();
+                self.send_input_query(Some(0));
+                rendering_state.new_cursor_selection = Some((0, 0));
+                AppControlFlow::Continue
+            }
+            covey::Action::Prompt(prompt) => {
+                // Only one prompt can be shown at a time.
+                if let Some(previous) = self.prompt.replace(PromptForm::new(prompt)) {
+                    self.host.answer_prompt(previous.prompt(), None);
+                }
+                AppControlFlow::OpenGui
+            }
             covey::Action::SetInput(covey::Input {
                 contents,
                 selection: (min, max),
             }) => {
                 // Another query to update the plugin on what it changed.
                 // This change isn't detected by text_edit.response.changed()
-                if contents != self.input {
-                    self.host.send_query(contents.clone());
-                }
+                let changed = contents != self.input;
                 self.input = contents;
+                if changed {
+                    self.send_input_query(Some(max));
+                }
                 rendering_state.new_cursor_selection = Some((min, max));
                 AppControlFlow::Continue
             }
             covey::Action::SetList(list) => {
                 tracing::debug!("received list with {} items", list.len());
+                self.list_selection = self.selection_in_new_list(&list).unwrap_or(0);
+                self.marked.clear();
                 self.list = Some(list);
-                self.list_selection = 0;
                 rendering_state.list_selection_changed = true;
                 AppControlFlow::Continue
             }
+            covey::Action::UpdateList(list) => {
+                tracing::trace!("received updated list with {} items", list.len());
+                self.list_selection = self
+                    .selection_in_new_list(&list)
+                    .unwrap_or_else(|| self.list_selection.min(list.len().saturating_sub(1)));
+                self.marked = self.marks_in_updated_list(&list);
+                self.list = Some(list);
+                AppControlFlow::Continue
+            }
+            covey::Action::ExtendList(extension) => {
+                tracing::debug!("received {} more list items", extension.items().len());
+                if let Some(list) = &mut self.list {
+                    list.extend(extension);
+                }
+                AppControlFlow::Continue
+            }
         }
     }
 
+    /// Queries the current input.
+    fn send_input_query(&mut self, cursor: Option<usize>) {
+        let is_first = !self.queried_since_open;
+        self.queried_since_open = true;
+        self.host
+            .send_query_with(self.input.clone(), covey::QueryOptions { cursor, is_first });
+    }
+
+    /// Index to select when `list` replaces the current list.
+    ///
+    /// Returns [`None`] if the plugin hasn't requested a selection and the
+    /// selected item's key isn't in the new list.
+    fn selection_in_new_list(&self, list: &covey::List) -> Option<usize> {
+        list.requested_selection().or_else(|| {
+            let current = self.list.as_ref()?;
+            if current.plugin() != list.plugin() {
+                return None;
+            }
+            let key = current.get(self.list_selection)?.key()?;
+            list.position_of_key(key)
+        })
+    }
+
+    /// Indices of the marked items in `list`, which is an update of the
+    /// current list.
+    ///
+    /// Items are found by their key, so marked items without a key are
+    /// unmarked.
+    fn marks_in_updated_list(&self, list: &covey::List) -> BTreeSet<usize> {
+        let Some(current) = &self.list else {
+            return BTreeSet::new();
+        };
+        self.marked
+            .iter()
+            .filter_map(|&i| current.get(i)?.key())
+            .filter_map(|key| list.position_of_key(key))
+            .collect()
+    }
+
     fn handle_keyboard_input(&mut self, ui: &mut Ui, rendering_state: &mut RenderingState) {
         // global hotkeys
 
-        if hotkeys::key_pressed_consume(ui, Key::Escape) {
+        if hotkeys::key_pressed_consume(ui, Key::Escape) && !self.pop_view(rendering_state) {
             ui.send_viewport_cmd(egui::ViewportCommand::Close);
         }
+        if self.input.is_empty() && hotkeys::key_pressed_consume(ui, Key::Backspace) {
+            self.pop_view(rendering_state);
+        }
 
         if let Some(list) = &self.list {
             if hotkeys::key_pressed_consume(ui, Key::ArrowDown) {
@@ -375,12 +490,17 @@ impl App {
             } else if hotkeys::key_pressed_consume(ui, Key::ArrowUp) {
                 self.list_selection = bounded_wrapping_sub(self.list_selection, 1, list.len());
                 rendering_state.list_selection_changed = true;
+            } else if hotkeys::hotkey_pressed_consume(ui, self.host.config().app.toggle_mark_hotkey)
+            {
+                if !self.marked.remove(&self.list_selection) {
+                    self.marked.insert(self.list_selection);
+                }
             } else if hotkeys::hotkey_pressed_consume(ui, self.host.config().app.reload_hotkey) {
                 let plugin_to_reload = list.plugin().id().clone();
                 // avoid activating now stale items
                 self.list = None;
                 self.host.reload_plugin(&plugin_to_reload);
-                self.host.send_query(self.input.clone());
+                self.send_input_query(None);
             }
         }
 
@@ -393,8 +513,11 @@ impl App {
             let activated_command = list
                 .get(self.list_selection)
                 .and_then(|item| {
-                    self.host
-                        .activate_by_hotkey(item.activation_target(), hotkey)
+                    let command = item
+                        .activation_target()
+                        .activated_command_from_hotkey(hotkey)?;
+                    activate_item_command(&mut self.host, list, &self.marked, item, &command.id);
+                    Some(command.id.clone())
                 })
                 .or_else(|| {
                     self.host
@@ -407,6 +530,35 @@ impl App {
         }
     }
 
+    /// Goes back to the previous list, returning whether there was a view to
+    /// go back from.
+    fn pop_view(&mut self, rendering_state: &mut RenderingState) -> bool {
+        let Some(input) = self.host.pop_view() else {
+            return false;
+        };
+        let input_len = input.chars().count();
+        self.input = input;
+        self.send_input_query(Some(input_len));
+        rendering_state.new_cursor_selection = Some((input_len, input_len));
+        true
+    }
+
+    fn show_breadcrumbs(&self, ui: &mut Ui) {
+        let views = self.host.views();
+        let Some(first) = views.first() else { return };
+
+        let crumbs = std::iter::once(first.plugin().manifest().name.as_str())
+            .chain(views.iter().map(covey::View::title))
+            .collect::<Vec<_>>()
+            .join(" › ");
+        ui.label(
+            RichText::new(crumbs)
+                .font(EYEBROW_TEXT_STYLE.resolve(ui.style()))
+                .color(self.style().weak_text_color().as_egui()),
+        );
+        ui.add_space(self.style().main_component_gap());
+    }
+
     fn show_input(&mut self, ui: &mut Ui, rendering_state: &RenderingState) {
         ui.spacing_mut().item_spacing = Vec2::ZERO;
         let mut text_edit = Container::new()
@@ -425,11 +577,9 @@ impl App {
                     .exact_size(Vec2::splat(icon_size))
                     .sense(Sense::empty())
                     .show(ui, |ui| {
-                        if self
-                            .list
-                            .as_ref()
-                            .is_none_or(|l| l.is_response_of_latest_query(&self.host))
-                        {
+                        if self.list.as_ref().is_none_or(|l| {
+                            l.is_response_of_latest_query(&self.host) && l.is_complete()
+                        }) {
                             if let Ok(icon) = ImageIcon::from_icon_name(
                                 &self.host,
                                 "search",
@@ -475,7 +625,8 @@ impl App {
         }
 
         if text_edit.response.changed() {
-            self.host.send_query(self.input.clone());
+            let cursor = text_edit.cursor_range.map(|range| range.primary.index);
+            self.send_input_query(cursor);
         }
 
         // can't request focus if the app is unfocused
@@ -491,46 +642,92 @@ impl App {
         let Some(list) = &mut self.list else { return };
         let s = &self.host.config().style;
 
-        ui.allocate_ui(Vec2::new(s.inner_width(), s.max_list_height()), |ui| {
-            ScrollArea::vertical()
-                // take up full width but shrink height
-                .auto_shrink(Vec2b::new(false, true))
-                .max_height(s.max_list_height())
-                .show(ui, |ui| {
-                    ui.spacing_mut().item_spacing = Vec2::splat(s.list_item_gap());
-
-                    for (i, item) in list.items().iter().enumerate() {
-                        if let Some(title) = list.section_title_at(i) {
-                            // TODO: make this bold
-                            Container::new()
-                                .inner_margin(Margin::symmetric(
-                                    s.list_item_padding().inline.saturating_as(),
-                                    0,
-                                ))
-                                .show(ui, |ui| {
-                                    ui.label(
-                                        egui::RichText::new(title)
-                                            .font(EYEBROW_TEXT_STYLE.resolve(ui.style())),
-                                    )
-                                });
-                        }
+        let preview = list
+            .get(self.list_selection)
+            .and_then(covey::ListItem::preview);
+        let list_width = match preview {
+            Some(_) => s.inner_width() - s.preview_width() - s.main_component_gap(),
+            None => s.inner_width(),
+        };
 
-                        let response = ListCell::new(&mut self.list_selection, i, item)
-                            .show(&self.host, ui, s);
+        ui.allocate_ui(Vec2::new(s.inner_width(), s.max_list_height()), |ui| {
+            ui.horizontal_top(|ui| {
+                ui.spacing_mut().item_spacing = Vec2::splat(s.main_component_gap());
+
+                ui.allocate_ui(Vec2::new(list_width, s.max_list_height()), |ui| {
+                    ScrollArea::vertical()
+                        // take up full width but shrink height
+                        .auto_shrink(Vec2b::new(false, true))
+                        .max_height(s.max_list_height())
+                        .show(ui, |ui| {
+                            ui.spacing_mut().item_spacing = Vec2::splat(s.list_item_gap());
+
+                            for (i, item) in list.items().iter().enumerate() {
+                                if let Some(title) = list.section_title_at(i) {
+                                    // TODO: make this bold
+                                    Container::new()
+                                        .inner_margin(Margin::symmetric(
+                                            s.list_item_padding().inline.saturating_as(),
+                                            0,
+                                        ))
+                                        .show(ui, |ui| {
+                                            ui.label(
+                                                egui::RichText::new(title)
+                                                    .font(EYEBROW_TEXT_STYLE.resolve(ui.style())),
+                                            )
+                                        });
+                                }
+
+                                let previous_selection = self.list_selection;
+                                let response = ListCell::new(&mut self.list_selection, i, item)
+                                    .marked(self.marked.contains(&i))
+                                    .show(&self.host, ui, s);
+
+                                if response.clicked() {
+                                    let modifiers = ui.input(|input| input.modifiers);
+                                    if modifiers.shift {
+                                        let start = previous_selection.min(i);
+                                        let end = previous_selection.max(i);
+                                        self.marked.extend(start..=end);
+                                    } else if modifiers.command {
+                                        if self.marked.is_empty() {
+                                            self.marked.insert(previous_selection);
+                                        }
+                                        if !self.marked.remove(&i) {
+                                            self.marked.insert(i);
+                                        }
+                                    }
+                                }
+
+                                if rendering_state.list_selection_changed
+                                    && i == self.list_selection
+                                {
+                                    tracing::info!("list selection changed");
+                                    response.scroll_to_me_animation(
+                                        None, // Don't scroll if already visible.
+                                        ScrollAnimation::duration(0.2),
+                                    );
+                                }
+                            }
+                        })
+                });
 
-                        if rendering_state.list_selection_changed && i == self.list_selection {
-                            tracing::info!("list selection changed");
-                            response.scroll_to_me_animation(
-                                None, // Don't scroll if already visible.
-                                ScrollAnimation::duration(0.2),
-                            );
-                        }
-                    }
-                })
+                if let Some(preview) = preview {
+                    ui.allocate_ui(Vec2::new(s.preview_width(), s.max_list_height()), |ui| {
+                        ScrollArea::vertical()
+                            .id_salt("preview")
+                            .auto_shrink(Vec2b::new(false, true))
+                            .max_height(s.max_list_height())
+                            .show(ui, |ui| PreviewPane::new(preview).show(ui, s));
+                    });
+                }
+            })
         });
     }
 
     fn show_buttom_bar(&mut self, ui: &mut Ui) {
+        self.show_progress(ui);
+
         ui.horizontal(|ui| {
             match &self.list {
                 None => {
@@ -544,37 +741,55 @@ impl App {
                     // subsequent commands.
                     let mut used_hotkeys = HashSet::<Hotkey>::new();
 
-                    if let Some(selected_item) = list.get(self.list_selection) {
-                        show_command_buttons(
-                            &mut self.host,
+                    if let Some(selected_item) = list.get(self.list_selection)
+                        && let Some(command_id) = show_command_buttons(
+                            &self.host,
                             ui,
                             selected_item.activation_target(),
                             &mut used_hotkeys,
+                        )
+                    {
+                        activate_item_command(
+                            &mut self.host,
+                            list,
+                            &self.marked,
+                            selected_item,
+                            &command_id,
                         );
                     };
 
                     // list commands are lower priority than list item commands.
-                    show_command_buttons(
-                        &mut self.host,
+                    if let Some(command_id) = show_command_buttons(
+                        &self.host,
                         ui,
                         list.activation_target(),
                         &mut used_hotkeys,
-                    );
+                    ) {
+                        self.host.activate(list.activation_target(), &command_id);
+                    }
 
                     // TODO: make clicking this go to a settings window
                     ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
                         ui.add(egui::Button::new(&list.plugin().manifest().name));
+                        if !self.marked.is_empty() {
+                            ui.colored_label(
+                                self.style().weak_text_color().as_egui(),
+                                format!("{} marked", self.marked.len()),
+                            );
+                        }
                     });
                 }
             }
         });
 
+        /// Returns the command that was clicked, if any.
         fn show_command_buttons(
-            host: &mut covey::Host,
+            host: &covey::Host,
             ui: &mut Ui,
             activation_target: &ActivationTarget,
             used_hotkeys: &mut HashSet<Hotkey>,
-        ) {
+        ) -> Option<CommandId> {
+            let mut clicked = None;
             for command in activation_target.available_commands() {
                 let s = &host.config().style;
 
@@ -589,8 +804,7 @@ impl App {
                         ui.style_mut().spacing.item_spacing = s.info_button_padding().as_egui();
                         ui.label(&command.title);
 
-                        if let Some(hotkeys) =
-                            activation_target.plugin().hotkeys_of_cmd(&command.id)
+                        if let Some(hotkeys) = activation_target.hotkeys_of_cmd(&command.id)
                             && let Some(new_hotkey) =
                                 hotkeys.iter().find(|hotkey| !used_hotkeys.contains(hotkey))
                         {
@@ -603,9 +817,62 @@ impl App {
                     });
 
                 if button.response.clicked() {
-                    host.activate(activation_target, &command.id);
+                    clicked = Some(command.id.clone());
                 }
             }
+            clicked
+        }
+    }
+
+    /// Shows the progress of the latest running command, if any.
+    fn show_progress(&mut self, ui: &mut Ui) {
+        let Some((task, progress)) = self.tasks.last() else {
+            return;
+        };
+        let s = &self.host.config().style;
+
+        let cancel_clicked = ui
+            .horizontal(|ui| {
+                ui.spacing_mut().item_spacing = Vec2::splat(s.info_button_gap());
+
+                // cancel button on the right, the progress takes up the rest
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    let cancel = Container::new()
+                        .inner_margin(s.info_button_padding().as_egui())
+                        .corner_radius(s.info_button_rounding().into())
+                        .fill(s.info_button_bg().as_egui())
+                        .hover_fill(s.info_button_hovered_bg().as_egui())
+                        .active_fill(s.info_button_active_bg().as_egui())
+                        .show(ui, |ui| ui.label("Cancel"));
+
+                    ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+                        match progress.fraction {
+                            Some(fraction) => {
+                                ui.add(
+                                    egui::ProgressBar::new(fraction)
+                                        .text(&progress.message)
+                                        .fill(s.list_item_active_bg().as_egui())
+                                        .corner_radius(s.info_button_rounding()),
+                                );
+                            }
+                            None => {
+                                ui.add(egui::Spinner::new().color(s.text_color().as_egui()));
+                                ui.label(&progress.message);
+                            }
+                        }
+                    });
+
+                    cancel.response.clicked()
+                })
+                .inner
+            })
+            .inner;
+        ui.add_space(s.main_component_gap());
+
+        if cancel_clicked {
+            let task = task.clone();
+            self.host.cancel_task(&task);
+            self.tasks.retain(|(t, _)| *t != task);
         }
     }
 }
@@ -624,6 +891,85 @@ pub enum AppControlFlow {
     ExitProcess,
 }
 
+/// Sets the clipboard with `arboard`, which supports more than plain text.
+fn set_clipboard(content: covey::ClipboardContent) -> anyhow::Result<()> {
+    let mut clipboard = arboard::Clipboard::new()?;
+    match content {
+        covey::ClipboardContent::Text(text) => clipboard.set_text(text)?,
+        covey::ClipboardContent::Html { html, alt_text } => clipboard.set_html(html, alt_text)?,
+        covey::ClipboardContent::Image(bytes) => {
+            let image = image::load_from_memory(&bytes)?.into_rgba8();
+            clipboard.set_image(arboard::ImageData {
+                width: image.width().saturating_as(),
+                height: image.height().saturating_as(),
+                bytes: image.into_raw().into(),
+            })?;
+        }
+        covey::ClipboardContent::Files(paths) => clipboard.set().file_list(&paths)?,
+    }
+    Ok(())
+}
+
+/// Shows a desktop notification with an icon and urgency matching the
+/// severity.
+fn show_notification(notification: covey::Notification) {
+    let covey::Notification {
+        severity,
+        title,
+        body,
+        timeout,
+    } = notification;
+
+    let (icon, urgency) = match severity {
+        covey::Severity::Info => ("dialog-information", notify_rust::Urgency::Low),
+        covey::Severity::Success => ("emblem-ok-symbolic", notify_rust::Urgency::Normal),
+        covey::Severity::Warning => ("dialog-warning", notify_rust::Urgency::Normal),
+        covey::Severity::Error => ("dialog-error", notify_rust::Urgency::Critical),
+    };
+
+    tokio::spawn(async move {
+        let mut builder = notify_rust::Notification::new();
+        builder
+            .summary(&title)
+            .body(&body)
+            .icon(icon)
+            .urgency(urgency);
+        if let Some(timeout) = timeout {
+            builder.timeout(timeout);
+        }
+
+        _ = builder.show_async().await.inspect_err(|e| {
+            tracing::error!("failed to display notification: {e:#}");
+            tracing::error!("notification was:\n{title}\n{body}");
+        });
+    });
+}
+
+/// Activates a list item command on the marked items, or on `selected` if
+/// no items are marked.
+///
+/// Marked items that don't have the command are skipped.
+fn activate_item_command(
+    host: &mut covey::Host,
+    list: &covey::List,
+    marked: &BTreeSet<usize>,
+    selected: &covey::ListItem,
+    command_id: &CommandId,
+) {
+    if marked.is_empty() {
+        host.activate(selected.activation_target(), command_id);
+        return;
+    }
+
+    let targets: Vec<_> = marked
+        .iter()
+        .filter_map(|&i| list.get(i))
+        .map(covey::ListItem::activation_target)
+        .filter(|target| target.available_commands().any(|cmd| cmd.id == *command_id))
+        .collect();
+    host.activate_many(&targets, command_id);
+}
+
 fn bounded_wrapping_add(x: usize, amount: usize, max_excl: usize) -> usize {
     if max_excl == 0 {
         return 0;
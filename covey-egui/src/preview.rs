@@ -0,0 +1,124 @@
+use covey::{Preview, covey_schema::style::UserStyle};
+use egui::{FontFamily, FontId, RichText, TextStyle, Ui, Vec2};
+
+use crate::{AsEgui, EYEBROW_TEXT_STYLE, widgets::Container};
+
+/// Contents of the preview pane for the selected list item.
+pub(crate) struct PreviewPane<'a> {
+    preview: &'a Preview,
+}
+
+impl<'a> PreviewPane<'a> {
+    pub(crate) fn new(preview: &'a Preview) -> Self {
+        Self { preview }
+    }
+
+    pub(crate) fn show(self, ui: &mut Ui, style: &UserStyle) {
+        ui.spacing_mut().item_spacing = Vec2::splat(style.list_item_padding().block);
+
+        match self.preview {
+            Preview::Markdown(text) => show_markdown(ui, style, text),
+            Preview::Code { text, language } => {
+                show_code_block(ui, style, text, language.as_deref());
+            }
+            Preview::Image(path) => {
+                ui.add(egui::Image::new(format!("file://{}", path.display())).shrink_to_fit());
+            }
+            Preview::Metadata(entries) => {
+                egui::Grid::new("preview-metadata")
+                    .num_columns(2)
+                    .spacing(AsEgui::<Vec2>::as_egui(&style.list_item_padding()))
+                    .show(ui, |ui| {
+                        for (key, value) in entries {
+                            ui.colored_label(style.weak_text_color().as_egui(), key);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+            }
+            _ => {
+                ui.colored_label(
+                    style.weak_text_color().as_egui(),
+                    "Preview type is not supported",
+                );
+            }
+        }
+    }
+}
+
+/// Renders a small subset of markdown: headings, lists, code blocks and
+/// paragraphs. Inline formatting is shown as-is.
+fn show_markdown(ui: &mut Ui, style: &UserStyle, text: &str) {
+    let mut code_block: Option<(Option<&str>, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        if let Some(fence) = line.trim_start().strip_prefix("```") {
+            match code_block.take() {
+                Some((language, lines)) => show_code_block(ui, style, &lines.join("\n"), language),
+                None => code_block = Some((Some(fence.trim()).filter(|l| !l.is_empty()), vec![])),
+            }
+            continue;
+        }
+        if let Some((_, lines)) = &mut code_block {
+            lines.push(line);
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            ui.add_space(style.list_item_padding().block);
+        } else if let Some((level, heading)) = heading(trimmed) {
+            let scale = match level {
+                1 => 1.4,
+                2 => 1.2,
+                _ => 1.0,
+            };
+            ui.label(RichText::new(heading).strong().font(FontId::new(
+                style.font_size() * scale,
+                FontFamily::Proportional,
+            )));
+        } else if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|bullet| trimmed.strip_prefix(bullet))
+        {
+            ui.label(format!("• {item}"));
+        } else {
+            ui.label(trimmed);
+        }
+    }
+
+    // Unclosed code block goes until the end.
+    if let Some((language, lines)) = code_block {
+        show_code_block(ui, style, &lines.join("\n"), language);
+    }
+}
+
+/// Returns the heading level and text if this line is a heading.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let text = line[level..].strip_prefix(' ')?;
+    Some((level, text.trim()))
+}
+
+fn show_code_block(ui: &mut Ui, style: &UserStyle, text: &str, language: Option<&str>) {
+    if let Some(language) = language {
+        ui.label(
+            RichText::new(language)
+                .font(EYEBROW_TEXT_STYLE.resolve(ui.style()))
+                .color(style.weak_text_color().as_egui()),
+        );
+    }
+
+    Container::new()
+        .fill(style.list_item_hovered_bg().as_egui())
+        .inner_margin(style.list_item_padding().as_egui())
+        .corner_radius(style.list_item_rounding().into())
+        .sense(egui::Sense::hover())
+        .min_size(Vec2::new(ui.available_width(), 0.0))
+        .show(ui, |ui| {
+            ui.label(RichText::new(text).font(TextStyle::Monospace.resolve(ui.style())));
+        });
+}
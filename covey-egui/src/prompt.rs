@@ -0,0 +1,140 @@
+use az::SaturatingAs as _;
+use covey::{Prompt, PromptAnswer, PromptKind, covey_schema::style::UserStyle};
+use egui::{Key, Margin, RichText, Sense, TextEdit, Ui, Vec2};
+
+use crate::{
+    AsEgui, EYEBROW_TEXT_STYLE, bounded_wrapping_add, bounded_wrapping_sub, hotkeys,
+    widgets::Container,
+};
+
+/// A question from a plugin that replaces the main UI until it is answered.
+pub(crate) struct PromptForm {
+    prompt: Prompt,
+    /// Contents of the text input for [`PromptKind::Text`].
+    text: String,
+    /// Selected option for [`PromptKind::Choice`] and [`PromptKind::Confirm`].
+    selection: usize,
+}
+
+impl PromptForm {
+    pub(crate) fn new(prompt: Prompt) -> Self {
+        let text = match prompt.kind() {
+            PromptKind::Text { initial, .. } => initial.clone(),
+            _ => String::new(),
+        };
+        Self {
+            prompt,
+            text,
+            selection: 0,
+        }
+    }
+
+    pub(crate) fn prompt(&self) -> &Prompt {
+        &self.prompt
+    }
+
+    fn answer(&self) -> PromptAnswer {
+        match self.prompt.kind() {
+            PromptKind::Text { .. } => PromptAnswer::Text(self.text.clone()),
+            PromptKind::Choice(_) => PromptAnswer::Choice(self.selection),
+            PromptKind::Confirm => PromptAnswer::Confirm(self.selection == 0),
+        }
+    }
+
+    pub(crate) fn show(&mut self, ui: &mut Ui, style: &UserStyle) -> PromptState {
+        let options = options(self.prompt.kind());
+        let option_count = options.len();
+
+        if hotkeys::key_pressed_consume(ui, Key::Escape) {
+            return PromptState::Closed(None);
+        } else if hotkeys::key_pressed_consume(ui, Key::ArrowDown) {
+            self.selection = bounded_wrapping_add(self.selection, 1, option_count);
+        } else if hotkeys::key_pressed_consume(ui, Key::ArrowUp) {
+            self.selection = bounded_wrapping_sub(self.selection, 1, option_count);
+        }
+        let mut submitted = hotkeys::key_pressed_consume(ui, Key::Enter);
+
+        ui.spacing_mut().item_spacing = Vec2::splat(style.main_component_gap());
+        ui.label(
+            RichText::new(self.prompt.plugin().manifest().name.as_str())
+                .font(EYEBROW_TEXT_STYLE.resolve(ui.style()))
+                .color(style.weak_text_color().as_egui()),
+        );
+        ui.label(RichText::new(self.prompt.title()).strong());
+
+        match self.prompt.kind() {
+            PromptKind::Text { placeholder, .. } => {
+                let response = Container::new()
+                    .exact_height(style.input_height())
+                    .inner_margin(Margin::symmetric(
+                        style.list_item_padding().inline.saturating_as(),
+                        0,
+                    ))
+                    .fill(style.list_item_hovered_bg().as_egui())
+                    .corner_radius(style.list_item_rounding().into())
+                    .sense(Sense::empty())
+                    .show_with_layout(ui, egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                        TextEdit::singleline(&mut self.text)
+                            .id_salt("covey prompt input")
+                            .hint_text(
+                                RichText::new(placeholder).color(style.weak_text_color().as_egui()),
+                            )
+                            .margin(Margin::ZERO)
+                            .desired_width(f32::INFINITY)
+                            .return_key(None)
+                            .show(ui)
+                    })
+                    .inner
+                    .response;
+                if !response.has_focus() {
+                    response.request_focus();
+                }
+            }
+            PromptKind::Choice(_) | PromptKind::Confirm => {
+                ui.spacing_mut().item_spacing = Vec2::splat(style.list_item_gap());
+                for (i, option) in options.into_iter().enumerate() {
+                    let button = Container::new()
+                        .fill(style.list_item_bg().as_egui())
+                        .hover_fill(style.list_item_hovered_bg().as_egui())
+                        .active_fill(style.list_item_active_bg().as_egui())
+                        .inner_margin(style.list_item_padding().as_egui())
+                        .corner_radius(style.list_item_rounding().into())
+                        .selected(self.selection == i)
+                        .min_size(Vec2::new(ui.available_width(), 0.0))
+                        .show(ui, |ui| ui.label(option));
+                    if button.response.clicked() {
+                        self.selection = i;
+                        submitted = true;
+                    }
+                }
+            }
+        }
+
+        ui.colored_label(
+            style.weak_text_color().as_egui(),
+            "Enter to confirm, Escape to cancel",
+        );
+
+        if submitted {
+            PromptState::Closed(Some(self.answer()))
+        } else {
+            PromptState::Open
+        }
+    }
+}
+
+pub(crate) enum PromptState {
+    /// Still waiting for the user.
+    Open,
+    /// The user has answered the prompt, or dismissed it if [`None`].
+    Closed(Option<PromptAnswer>),
+}
+
+/// Options that can be selected for the prompt kind.
+fn options(kind: &PromptKind) -> Vec<&str> {
+    match kind {
+        PromptKind::Choice(options) => options.iter().map(String::as_str).collect(),
+        PromptKind::Confirm => vec!["Yes", "No"],
+        PromptKind::Text { .. } => vec![],
+    }
+}
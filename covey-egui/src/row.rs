@@ -1,5 +1,8 @@
+use std::ops::Range;
+
+use az::SaturatingAs as _;
 use covey::{Host, ListItem, covey_schema::style::UserStyle};
-use egui::{TextStyle, Ui, Vec2};
+use egui::{Color32, FontId, Stroke, TextFormat, TextStyle, Ui, Vec2, text::LayoutJob};
 
 use crate::{
     AsEgui, ICON_TEXT_STYLE,
@@ -10,6 +13,8 @@ pub(crate) struct ListCell<'sel, 'item, Value> {
     current_value: &'sel mut Value,
     selected_value: Value,
     item: &'item ListItem,
+    /// Whether the item is marked for batch activation.
+    marked: bool,
 }
 
 impl<'sel, 'item, Value: PartialEq> ListCell<'sel, 'item, Value> {
@@ -22,9 +27,15 @@ impl<'sel, 'item, Value: PartialEq> ListCell<'sel, 'item, Value> {
             current_value,
             selected_value,
             item,
+            marked: false,
         }
     }
 
+    pub(crate) fn marked(mut self, marked: bool) -> Self {
+        self.marked = marked;
+        self
+    }
+
     pub(crate) fn show(self, host: &Host, ui: &mut Ui, style: &UserStyle) -> egui::Response {
         let mut button = Container::new()
             .fill(style.list_item_bg().as_egui())
@@ -32,6 +43,11 @@ impl<'sel, 'item, Value: PartialEq> ListCell<'sel, 'item, Value> {
             .active_fill(style.list_item_active_bg().as_egui())
             .inner_margin(style.list_item_padding().as_egui())
             .corner_radius(style.list_item_rounding().into())
+            .stroke(if self.marked {
+                Stroke::new(1.0, style.match_highlight_color().as_egui())
+            } else {
+                Stroke::NONE
+            })
             .selected(*self.current_value == self.selected_value)
             .min_size(Vec2::new(ui.available_width(), 0.0))
             .show_horizontal(ui, |ui| {
@@ -45,17 +61,42 @@ impl<'sel, 'item, Value: PartialEq> ListCell<'sel, 'item, Value> {
                 ui.vertical(|ui| {
                     ui.spacing_mut().item_spacing = Vec2::ZERO;
 
-                    ui.label(self.item.title());
+                    ui.label(highlighted_text(
+                        self.item.title(),
+                        self.item.title_highlights(),
+                        &TextStyle::Body.resolve(ui.style()),
+                        style.text_color().as_egui(),
+                        style.match_highlight_color().as_egui(),
+                    ));
 
                     if !self.item.description().is_empty() {
                         ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Truncate);
-                        ui.label(
-                            egui::RichText::new(self.item.description())
-                                .font(TextStyle::Small.resolve(ui.style()))
-                                .color(style.weak_text_color().as_egui()),
-                        );
+                        ui.label(highlighted_text(
+                            self.item.description(),
+                            self.item.description_highlights(),
+                            &TextStyle::Small.resolve(ui.style()),
+                            style.weak_text_color().as_egui(),
+                            style.match_highlight_color().as_egui(),
+                        ));
                     }
-                })
+                });
+
+                if self.item.accessory().is_some() || !self.item.tags().is_empty() {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if let Some(accessory) = self.item.accessory() {
+                            ui.label(
+                                egui::RichText::new(accessory)
+                                    .font(TextStyle::Small.resolve(ui.style()))
+                                    .color(style.weak_text_color().as_egui()),
+                            );
+                        }
+
+                        // right to left, so reverse to keep the plugin's order
+                        for tag in self.item.tags().iter().rev() {
+                            ui.add(TagChip::new(tag, style));
+                        }
+                    });
+                }
             })
             .response;
 
@@ -69,6 +110,74 @@ impl<'sel, 'item, Value: PartialEq> ListCell<'sel, 'item, Value> {
     }
 }
 
+/// Lays out text with some character ranges in a different color.
+fn highlighted_text(
+    text: &str,
+    highlights: &[Range<usize>],
+    font_id: &FontId,
+    color: Color32,
+    highlight_color: Color32,
+) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let mut append = |str: &str, color: Color32| {
+        job.append(str, 0.0, TextFormat::simple(font_id.clone(), color));
+    };
+
+    // Convert char ranges to byte indices.
+    let byte_index = |char_index: usize| {
+        text.char_indices()
+            .nth(char_index)
+            .map_or(text.len(), |(i, _)| i)
+    };
+
+    let mut last_end = 0;
+    for range in highlights {
+        let start = byte_index(range.start).max(last_end);
+        let end = byte_index(range.end).max(start);
+        append(&text[last_end..start], color);
+        append(&text[start..end], highlight_color);
+        last_end = end;
+    }
+    append(&text[last_end..], color);
+
+    job
+}
+
+struct TagChip<'a> {
+    tag: &'a covey::Tag,
+    style: &'a UserStyle,
+}
+
+impl<'a> TagChip<'a> {
+    fn new(tag: &'a covey::Tag, style: &'a UserStyle) -> Self {
+        Self { tag, style }
+    }
+}
+
+impl egui::Widget for TagChip<'_> {
+    fn ui(self, ui: &mut Ui) -> egui::Response {
+        let color = self.style.tag_color(self.tag.color());
+        let padding = self.style.list_item_padding();
+
+        Container::new()
+            .fill(color.multiply_alpha(0.2).as_egui())
+            .inner_margin(egui::Margin::symmetric(
+                padding.inline.saturating_as(),
+                (padding.block / 2.0).saturating_as(),
+            ))
+            .corner_radius(self.style.list_item_rounding().into())
+            .sense(egui::Sense::hover())
+            .show(ui, |ui| {
+                ui.label(
+                    egui::RichText::new(self.tag.text())
+                        .font(TextStyle::Small.resolve(ui.style()))
+                        .color(color.as_egui()),
+                )
+            })
+            .response
+    }
+}
+
 struct CellIcon {
     icon: covey::ResolvedIcon,
 }
@@ -87,6 +196,9 @@ impl egui::Widget for CellIcon {
             covey::ResolvedIcon::File(file_path) => {
                 ui.add(ImageIcon::from_file_path(file_path, icon_size))
             }
+            covey::ResolvedIcon::Bytes { uri, bytes } => {
+                ui.add(ImageIcon::from_bytes(uri, bytes, icon_size))
+            }
             covey::ResolvedIcon::Text(text) => ui.add_sized(
                 icon_size,
                 egui::Label::new(
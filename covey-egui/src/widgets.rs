@@ -1,8 +1,9 @@
-use std::path::PathBuf;
+use std::{path::Path, sync::Arc};
 
 use covey::{Icon, ResolveIconError};
 use egui::{
-    Color32, CornerRadius, Image, InnerResponse, Margin, NumExt, Sense, Stroke, Ui, Vec2, Widget,
+    Color32, CornerRadius, Image, ImageSource, InnerResponse, Margin, NumExt, Sense, Stroke, Ui,
+    Vec2, Widget,
 };
 
 // maybe make the hover and active states just more frames?
@@ -241,13 +242,27 @@ impl Container {
 }
 
 pub struct ImageIcon {
-    file_path: PathBuf,
+    source: ImageSource<'static>,
     size: Vec2,
 }
 
 impl ImageIcon {
-    pub fn from_file_path(file_path: PathBuf, size: Vec2) -> Self {
-        Self { file_path, size }
+    pub fn from_file_path(file_path: impl AsRef<Path>, size: Vec2) -> Self {
+        Self {
+            source: ImageSource::Uri(format!("file://{}", file_path.as_ref().display()).into()),
+            size,
+        }
+    }
+
+    /// `uri` should be unique to the contents of `bytes`.
+    pub fn from_bytes(uri: String, bytes: Arc<[u8]>, size: Vec2) -> Self {
+        Self {
+            source: ImageSource::Bytes {
+                uri: uri.into(),
+                bytes: bytes.into(),
+            },
+            size,
+        }
     }
 
     pub fn from_icon_name(
@@ -257,8 +272,8 @@ impl ImageIcon {
     ) -> Result<Self, ResolveIconError> {
         let path = match Icon::new_named(icon_name.to_owned()).resolve(host)? {
             covey::ResolvedIcon::File(path_buf) => path_buf,
-            covey::ResolvedIcon::Text(t) => {
-                unreachable!("named icon should resolve to file path, got text {t:?}")
+            other => {
+                unreachable!("named icon should resolve to file path, got {other:?}")
             }
         };
         Ok(Self::from_file_path(path, size))
@@ -269,7 +284,7 @@ impl Widget for ImageIcon {
     fn ui(self, ui: &mut Ui) -> egui::Response {
         ui.add_sized(
             self.size,
-            Image::new(format!("file://{}", self.file_path.display())).fit_to_exact_size(self.size),
+            Image::new(self.source).fit_to_exact_size(self.size),
         )
     }
 }
@@ -1,3 +1,5 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
 use crate::Input;
 
 /// An action for Covey to perform.
@@ -18,6 +20,14 @@ impl Action {
         Self(covey_proto::PluginAction::Copy(str.into()))
     }
 
+    /// Copies an image, HTML or files.
+    ///
+    /// Use [`Menu::copy_content`](crate::Menu::copy_content) to fall back to
+    /// plain text if Covey can't copy the content.
+    pub fn copy_content(content: ClipboardContent) -> Self {
+        Self(covey_proto::PluginAction::CopyContent(content.0))
+    }
+
     pub fn set_input(input: impl Into<Input>) -> Self {
         Self(covey_proto::PluginAction::SetInput(
             crate::into_proto::input(input.into()),
@@ -27,4 +37,125 @@ impl Action {
     pub fn display_error(err: impl std::fmt::Display) -> Self {
         Self(covey_proto::PluginAction::DisplayError(err.to_string()))
     }
+
+    /// Opens a URL with the opener configured in Covey.
+    ///
+    /// Prefer this over spawning `xdg-open` so that the user's settings are
+    /// used and failures are shown to the user. Use
+    /// [`Menu::open_url`](crate::Menu::open_url) to fall back to `xdg-open` if
+    /// Covey doesn't have an opener.
+    pub fn open_url(url: impl Into<String>) -> Self {
+        Self(covey_proto::PluginAction::OpenUrl(url.into()))
+    }
+
+    /// Opens a file or directory with the opener configured in Covey.
+    ///
+    /// The handler is chosen from the MIME type of the path. Use
+    /// [`Menu::open_path`](crate::Menu::open_path) to fall back to `xdg-open`
+    /// if Covey doesn't have an opener.
+    pub fn open_path(path: impl Into<PathBuf>) -> Self {
+        Self(covey_proto::PluginAction::OpenPath(path.into()))
+    }
+
+    pub fn notify(notification: Notification) -> Self {
+        Self(covey_proto::PluginAction::Notify(notification.0))
+    }
+}
+
+/// A message shown to the user, like "Copied 3 files".
+///
+/// Unlike [`Action::display_error`], this does not imply that the plugin
+/// failed.
+#[derive(Debug, Clone)]
+pub struct Notification(pub(crate) covey_proto::Notification);
+
+impl Notification {
+    fn new(severity: covey_proto::Severity, title: impl Into<String>) -> Self {
+        Self(covey_proto::Notification {
+            severity,
+            title: title.into(),
+            body: String::new(),
+            timeout_ms: None,
+        })
+    }
+
+    pub fn info(title: impl Into<String>) -> Self {
+        Self::new(covey_proto::Severity::Info, title)
+    }
+
+    pub fn success(title: impl Into<String>) -> Self {
+        Self::new(covey_proto::Severity::Success, title)
+    }
+
+    pub fn warning(title: impl Into<String>) -> Self {
+        Self::new(covey_proto::Severity::Warning, title)
+    }
+
+    pub fn error(title: impl Into<String>) -> Self {
+        Self::new(covey_proto::Severity::Error, title)
+    }
+
+    #[must_use = "builder method consumes self"]
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.0.body = body.into();
+        self
+    }
+
+    /// Hides the notification after this duration, instead of using the
+    /// frontend's default.
+    #[must_use = "builder method consumes self"]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.0.timeout_ms = Some(u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX));
+        self
+    }
+}
+
+/// Content to copy that isn't only plain text.
+#[derive(Debug, Clone)]
+pub struct ClipboardContent(pub(crate) covey_proto::ClipboardContent);
+
+impl ClipboardContent {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self(covey_proto::ClipboardContent::Text(text.into()))
+    }
+
+    /// HTML, with plain text for applications that can't paste HTML.
+    pub fn html(html: impl Into<String>, alt_text: impl Into<String>) -> Self {
+        Self(covey_proto::ClipboardContent::Html {
+            html: html.into(),
+            alt_text: Some(alt_text.into()),
+        })
+    }
+
+    /// Contents of an image file, such as a PNG or JPEG.
+    pub fn image(bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self(covey_proto::ClipboardContent::Image(bytes.into()))
+    }
+
+    /// Files to paste in a file manager or upload form.
+    ///
+    /// Paths should be absolute.
+    pub fn files(paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self(covey_proto::ClipboardContent::Files(
+            paths.into_iter().map(Into::into).collect(),
+        ))
+    }
+
+    /// Plain text to copy instead if the host can't copy this content.
+    pub(crate) fn fallback_text(&self) -> Option<String> {
+        match &self.0 {
+            covey_proto::ClipboardContent::Text(text) => Some(text.clone()),
+            covey_proto::ClipboardContent::Html { html, alt_text } => {
+                Some(alt_text.clone().unwrap_or_else(|| html.clone()))
+            }
+            covey_proto::ClipboardContent::Image(_) => None,
+            covey_proto::ClipboardContent::Files(paths) => Some(
+                paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        }
+    }
 }
@@ -0,0 +1,60 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+tokio::task_local! {
+    static CURRENT: Cancellation;
+}
+
+/// Signal for when covey no longer needs the result of a request.
+///
+/// Queries are cancelled when the user types another query. The future
+/// handling the query is dropped at its next `.await`, so most plugins
+/// don't need to do anything. Long running synchronous work (for example,
+/// in a plugin run with [`run_server_blocking`](crate::run_server_blocking))
+/// should check [`Cancellation::is_cancelled`] and return early.
+///
+/// # Examples
+/// ```ignore
+/// async fn query(&self, query: String) -> Result<List> {
+///     let cancellation = Cancellation::current();
+///     let mut items = vec![];
+///     for path in expensive_search() {
+///         if cancellation.is_cancelled() {
+///             break;
+///         }
+///         items.push(ListItem::new(path));
+///     }
+///     Ok(List::new(items))
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    /// Gets the cancellation signal of the request currently being handled.
+    ///
+    /// Outside of a request, this returns a signal that is never cancelled.
+    pub fn current() -> Self {
+        CURRENT.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Makes this the [`current`](Self::current) signal while running `f`.
+    pub(crate) async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// Makes this the [`current`](Self::current) signal while running `f`.
+    pub(crate) fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        CURRENT.sync_scope(self, f)
+    }
+}
@@ -0,0 +1,78 @@
+pub use covey_proto::Hotkey;
+
+/// A command that is created while the plugin is running, instead of being
+/// declared in the manifest.
+///
+/// Useful for commands that depend on data, like "Open with" for each
+/// installed editor. Add it to a list item with
+/// [`ListItem::with_command`](crate::ListItem::with_command).
+#[derive(Debug, Clone)]
+pub struct RuntimeCommand(pub(crate) covey_proto::Command);
+
+impl RuntimeCommand {
+    /// The ID should be unique within the list item, and different to the
+    /// IDs of commands in the manifest.
+    pub fn new(id: &str, title: impl Into<String>) -> Self {
+        Self(covey_proto::Command {
+            id: covey_proto::CommandId::new(id),
+            title: title.into(),
+            description: None,
+            default_hotkeys: None,
+        })
+    }
+
+    #[must_use = "builder method consumes self"]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.0.description = Some(description.into());
+        self
+    }
+
+    /// Adds a hotkey that activates this command.
+    ///
+    /// Unlike manifest commands, users can't change the hotkeys of runtime
+    /// commands.
+    #[must_use = "builder method consumes self"]
+    pub fn with_default_hotkey(mut self, hotkey: Hotkey) -> Self {
+        self.0.default_hotkeys.get_or_insert_default().push(hotkey);
+        self
+    }
+}
+
+/// Title of a manifest command to show for a single list item, instead of
+/// the title in the manifest.
+///
+/// Set with the `on_<command>_with_title` methods generated by
+/// [`include_manifest!`](crate::include_manifest!). Hotkeys are still taken
+/// from the manifest.
+#[derive(Debug, Clone)]
+pub struct CommandTitle {
+    pub(crate) title: String,
+    pub(crate) description: Option<String>,
+}
+
+impl CommandTitle {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: None,
+        }
+    }
+
+    #[must_use = "builder method consumes self"]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl From<String> for CommandTitle {
+    fn from(title: String) -> Self {
+        Self::new(title)
+    }
+}
+
+impl From<&str> for CommandTitle {
+    fn from(title: &str) -> Self {
+        Self::new(title)
+    }
+}
@@ -0,0 +1,68 @@
+tokio::task_local! {
+    static CURRENT: QueryContext;
+}
+
+/// Information about the query currently being handled, besides its text.
+///
+/// # Examples
+/// ```ignore
+/// async fn query(&self, query: String) -> Result<List> {
+///     let context = QueryContext::current();
+///     if context.is_first() {
+///         self.refresh_cache().await?;
+///     }
+///     Ok(List::new(self.search(&query)))
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct QueryContext(covey_proto::QueryContext);
+
+impl QueryContext {
+    /// Gets the context of the query currently being handled.
+    ///
+    /// Outside of [`Plugin::query`](crate::Plugin::query) or a view's query
+    /// callback, this returns an empty context.
+    pub fn current() -> Self {
+        CURRENT.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    /// The entire input, including the prefix.
+    ///
+    /// Older versions of Covey don't send the raw input, in which case this
+    /// is the query without the prefix.
+    pub fn raw_input(&self) -> &str {
+        &self.0.raw
+    }
+
+    /// The prefix that activated this plugin, which is removed from the
+    /// query.
+    ///
+    /// Empty when a view is queried.
+    pub fn prefix(&self) -> &str {
+        &self.0.prefix
+    }
+
+    /// Position of the cursor in [`Self::raw_input`], in characters.
+    pub fn cursor(&self) -> Option<usize> {
+        self.0.cursor
+    }
+
+    /// Whether this is the first query since Covey was opened.
+    pub fn is_first(&self) -> bool {
+        self.0.is_first
+    }
+
+    pub(crate) fn new(context: covey_proto::QueryContext) -> Self {
+        Self(context)
+    }
+
+    /// Makes this the [`current`](Self::current) context while running `f`.
+    pub(crate) async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// Makes this the [`current`](Self::current) context while running `f`.
+    pub(crate) fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        CURRENT.sync_scope(self, f)
+    }
+}
@@ -0,0 +1,64 @@
+use std::{path::PathBuf, sync::OnceLock};
+
+pub use covey_proto::{Color, Theme};
+
+static ENVIRONMENT: OnceLock<Environment> = OnceLock::new();
+
+/// Information about Covey, sent when the plugin starts.
+#[derive(Debug)]
+pub struct Environment {
+    covey_version: String,
+    data_dir: PathBuf,
+    locale: Option<String>,
+    theme: Theme,
+}
+
+impl Environment {
+    /// Gets the environment sent by Covey.
+    ///
+    /// Returns [`None`] before [`Plugin::new`](crate::Plugin::new) is called,
+    /// or if this version of Covey is too old to send it.
+    pub fn get() -> Option<&'static Self> {
+        ENVIRONMENT.get()
+    }
+
+    /// Version of Covey that started this plugin.
+    pub fn covey_version(&self) -> &str {
+        &self.covey_version
+    }
+
+    /// Same as [`plugin_data_dir`](crate::plugin_data_dir).
+    pub fn data_dir(&self) -> &PathBuf {
+        &self.data_dir
+    }
+
+    /// Language of the user, like `en-AU`, if it is known.
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// Colors of Covey's window.
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Stores everything from `initialize` except the settings.
+    pub(crate) fn set(initialize: covey_proto::Initialize) {
+        let covey_proto::Initialize {
+            settings: _,
+            covey_version,
+            data_dir,
+            locale,
+            theme,
+        } = initialize;
+
+        ENVIRONMENT
+            .set(Self {
+                covey_version,
+                data_dir,
+                locale,
+                theme,
+            })
+            .expect("environment should only be set from main");
+    }
+}
@@ -1,10 +1,51 @@
-pub(crate) fn icon(icon: crate::Icon) -> covey_proto::ListItemIcon {
+/// Hosts without [`covey_proto::Feature::RichIcons`] get the first name or
+/// text icon instead, or no icon if there isn't one.
+pub(crate) fn icon(
+    icon: crate::Icon,
+    host: &covey_proto::Hello,
+) -> Option<covey_proto::ListItemIcon> {
+    let rich_icons = host.supports(covey_proto::Feature::RichIcons);
     match icon {
-        crate::Icon::Name(name) => covey_proto::ListItemIcon::Name(name),
-        crate::Icon::Text(text) => covey_proto::ListItemIcon::Text(text),
+        crate::Icon::Name(name) => Some(covey_proto::ListItemIcon::Name(name)),
+        crate::Icon::Text(text) => Some(covey_proto::ListItemIcon::Text(text)),
+        crate::Icon::File(path) => rich_icons.then_some(covey_proto::ListItemIcon::File(path)),
+        crate::Icon::Data(bytes) => {
+            rich_icons.then(|| covey_proto::ListItemIcon::Data(bytes.into()))
+        }
+        crate::Icon::Fallback(icons) if rich_icons => Some(covey_proto::ListItemIcon::Fallback(
+            icons
+                .into_iter()
+                .filter_map(|icon| self::icon(icon, host))
+                .collect(),
+        )),
+        crate::Icon::Fallback(icons) => icons.into_iter().find_map(|icon| self::icon(icon, host)),
     }
 }
 
+pub(crate) fn preview(preview: crate::Preview) -> covey_proto::ListItemPreview {
+    match preview {
+        crate::Preview::Markdown(text) => covey_proto::ListItemPreview::Markdown(text),
+        crate::Preview::Code { text, language } => {
+            covey_proto::ListItemPreview::Code { text, language }
+        }
+        // Covey runs in a different working directory.
+        crate::Preview::Image(path) => {
+            covey_proto::ListItemPreview::Image(std::path::absolute(&path).unwrap_or(path))
+        }
+        crate::Preview::Metadata(entries) => covey_proto::ListItemPreview::Metadata(entries),
+    }
+}
+
+pub(crate) fn tag(tag: crate::Tag) -> covey_proto::ListItemTag {
+    let crate::Tag { text, color } = tag;
+    covey_proto::ListItemTag { text, color }
+}
+
+pub(crate) fn highlights(highlights: crate::rank::Highlights) -> covey_proto::ListItemHighlights {
+    let crate::rank::Highlights { title, description } = highlights;
+    covey_proto::ListItemHighlights { title, description }
+}
+
 pub(crate) fn action(action: crate::Action) -> covey_proto::PluginAction {
     action.0
 }
@@ -1,24 +1,40 @@
 mod action;
+mod cancel;
+mod command;
+mod context;
+mod environment;
 mod input;
 mod into_proto;
 mod list;
+mod log;
 pub mod manifest;
 mod menu;
 mod plugin;
+mod prompt;
 pub mod rank;
 mod server;
 pub mod spawn;
+pub mod storage;
+mod transport;
+mod view;
 
 use std::{
     path::PathBuf,
     sync::{LazyLock, OnceLock},
 };
 
-pub use action::Action;
+pub use action::{Action, ClipboardContent, Notification};
+pub use cancel::Cancellation;
+pub use command::{CommandTitle, Hotkey, RuntimeCommand};
+pub use context::QueryContext;
+pub use covey_proto::TagColor;
+pub use environment::{Color, Environment, Theme};
 pub use input::{Input, SelectionRange};
-pub use list::{Icon, List, ListItem, ListSection};
+pub use list::{Icon, List, ListItem, ListSection, ListSender, ListUpdater, Preview, Tag};
+pub use log::LogLayer;
 pub use menu::Menu;
 pub use plugin::Plugin;
+pub use prompt::{Prompt, PromptAnswer};
 pub use server::{run_server, run_server_blocking};
 mod store;
 
@@ -32,7 +48,8 @@ pub static PLUGIN_ID: OnceLock<&'static str> = OnceLock::new();
 
 /// Assigned directory of this plugin, where extra data can be stored.
 ///
-/// This is `<data-dir>/covey/plugins/<plugin-id>/`. The directory should
+/// This is sent by Covey, and is usually
+/// `<data-dir>/covey/plugins/<plugin-id>/`. The directory should
 /// already contain this plugin's binary (with the name of `<plugin-id>`) and
 /// a `manifest.toml`.
 ///
@@ -43,6 +60,10 @@ pub static PLUGIN_ID: OnceLock<&'static str> = OnceLock::new();
 /// called.
 pub fn plugin_data_dir() -> &'static PathBuf {
     static DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+        if let Some(environment) = Environment::get() {
+            return environment.data_dir().clone();
+        }
+
         dirs::data_dir()
             .expect("data dir should exist")
             .join("covey")
@@ -1,8 +1,10 @@
-use std::{collections::BTreeMap, time::SystemTime};
+use std::{collections::BTreeMap, path::PathBuf, sync::LazyLock, time::SystemTime};
+
+use tokio::sync::{mpsc, watch};
 
 use crate::{
-    Menu,
-    rank::{self, VisitId},
+    CommandTitle, Menu, RuntimeCommand, TagColor,
+    rank::{self, Highlights, VisitId},
     store::TargetCallbacks,
 };
 
@@ -11,6 +13,11 @@ pub struct List {
     pub(crate) items: Vec<ListItem>,
     pub(crate) section_titles: BTreeMap<usize, String>,
     pub(crate) callbacks: TargetCallbacks,
+    pub(crate) selection: Option<covey_proto::ListSelection>,
+    /// More items that will be added after this list is sent.
+    pub(crate) stream: Option<mpsc::UnboundedReceiver<ListSection>>,
+    /// Lists that replace this one after it is sent.
+    pub(crate) updates: Option<mpsc::UnboundedReceiver<List>>,
 }
 
 impl List {
@@ -19,26 +26,96 @@ impl List {
             items,
             section_titles: BTreeMap::new(),
             callbacks: TargetCallbacks::new(),
+            selection: None,
+            stream: None,
+            updates: None,
         }
     }
 
     pub fn from_sections(sections: impl IntoIterator<Item = ListSection>) -> Self {
-        let mut section_titles = BTreeMap::new();
-        let mut last_section_end = 0;
-        let mut items = vec![];
+        let mut this = Self::new(vec![]);
         for section in sections {
-            if !section.title.is_empty() {
-                section_titles.insert(last_section_end, section.title);
-            }
-            last_section_end += section.items.len();
-            items.extend(section.items);
+            this.push_section(section);
         }
+        this
+    }
 
-        Self {
-            items,
-            section_titles,
-            callbacks: TargetCallbacks::new(),
+    /// Adds a section to the end of this list.
+    pub(crate) fn push_section(&mut self, section: ListSection) {
+        if !section.title.is_empty() {
+            self.section_titles.insert(self.items.len(), section.title);
         }
+        self.items.extend(section.items);
+    }
+
+    /// Allows more items to be added to this list after it is returned from
+    /// [`Plugin::query`](crate::Plugin::query).
+    ///
+    /// The items in this list will be shown immediately. More items can be
+    /// added by the returned [`ListSender`], which will usually be moved into
+    /// a spawned task. The list is marked as complete once the sender is
+    /// dropped.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// async fn query(&self, query: String) -> Result<List> {
+    ///     let (list, sender) = List::new(quick_results(&query)).streamed();
+    ///     tokio::task::spawn_local(async move {
+    ///         sender.send_section(ListSection::new("Web", slow_results(&query).await));
+    ///     });
+    ///     Ok(list)
+    /// }
+    /// ```
+    pub fn streamed(mut self) -> (Self, ListSender) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.stream = Some(rx);
+        (self, ListSender { sections: tx })
+    }
+
+    /// Allows this list to be replaced while it is being shown, without the
+    /// user changing the query.
+    ///
+    /// Useful for lists that change by themselves, like a process monitor or
+    /// a timer. Replacement lists are sent with the returned [`ListUpdater`],
+    /// which will usually be moved into a spawned task.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// async fn query(&self, query: String) -> Result<List> {
+    ///     let (list, updater) = List::new(processes(&query)).live();
+    ///     tokio::task::spawn_local(async move {
+    ///         let mut interval = tokio::time::interval(Duration::from_secs(1));
+    ///         while !updater.is_closed() {
+    ///             interval.tick().await;
+    ///             updater.wait_until_shown().await;
+    ///             updater.set_list(List::new(processes(&query)));
+    ///         }
+    ///     });
+    ///     Ok(list)
+    /// }
+    /// ```
+    pub fn live(mut self) -> (Self, ListUpdater) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.updates = Some(rx);
+        (self, ListUpdater { lists: tx })
+    }
+
+    /// Selects the item at `index` when this list is shown.
+    ///
+    /// By default, the item with the same [`ListItem::key`] as the previously
+    /// selected item is selected, or the first item otherwise.
+    #[must_use = "builder method consumes self"]
+    pub fn with_selected_index(mut self, index: usize) -> Self {
+        self.selection = Some(covey_proto::ListSelection::Index(index));
+        self
+    }
+
+    /// Selects the first item with this [`ListItem::key`] when this list is
+    /// shown.
+    #[must_use = "builder method consumes self"]
+    pub fn with_selected_key(mut self, key: impl Into<String>) -> Self {
+        self.selection = Some(covey_proto::ListSelection::Key(key.into()));
+        self
     }
 
     pub fn len(&self) -> usize {
@@ -64,6 +141,132 @@ impl List {
             .add_callback(covey_proto::CommandId::new(name), callback);
         self
     }
+
+    /// Adds a command that can be called, shown with a different title.
+    ///
+    /// This should not be used directly, use the extension trait generated
+    /// by [`crate::include_manifest!`] instead.
+    #[doc(hidden)]
+    #[must_use]
+    pub fn add_command_with_title(
+        mut self,
+        name: &'static str,
+        title: impl Into<CommandTitle>,
+        callback: impl AsyncFn(&Menu) -> crate::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.callbacks.add_titled_callback(
+            covey_proto::CommandId::new(name),
+            title.into(),
+            callback,
+        );
+        self
+    }
+
+    /// Adds a callback that activates several items of this list at once.
+    ///
+    /// This should not be used directly, use the extension trait generated
+    /// by [`crate::include_manifest!`] instead.
+    #[doc(hidden)]
+    #[must_use]
+    pub fn add_batch_command(
+        mut self,
+        name: &'static str,
+        callback: impl AsyncFn(&Menu, Vec<String>) -> crate::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.callbacks
+            .add_batch_callback(covey_proto::CommandId::new(name), callback);
+        self
+    }
+
+    /// Adds a command that isn't declared in the manifest.
+    ///
+    /// The command isn't shown if Covey doesn't support runtime commands.
+    #[must_use = "builder method consumes self"]
+    pub fn with_command(
+        mut self,
+        command: RuntimeCommand,
+        callback: impl AsyncFn(&Menu) -> crate::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.callbacks.add_runtime_callback(command.0, callback);
+        self
+    }
+}
+
+/// Adds more items to the end of a streamed [`List`].
+///
+/// Created by [`List::streamed`]. The list is complete once this is dropped.
+pub struct ListSender {
+    sections: mpsc::UnboundedSender<ListSection>,
+}
+
+impl ListSender {
+    /// Appends items to the end of the list, continuing the last section.
+    pub fn send(&self, items: Vec<ListItem>) {
+        self.send_section(ListSection::unnamed(items));
+    }
+
+    /// Appends a new section to the end of the list.
+    ///
+    /// Sections with an empty title continue the last section.
+    pub fn send_section(&self, section: ListSection) {
+        // The list has been replaced if the receiver is gone, just ignore.
+        _ = self.sections.send(section);
+    }
+
+    /// Whether the list is no longer being shown, so there is no point
+    /// sending any more items.
+    pub fn is_closed(&self) -> bool {
+        self.sections.is_closed()
+    }
+}
+
+/// Replaces a live [`List`] while it is shown.
+///
+/// Created by [`List::live`].
+pub struct ListUpdater {
+    lists: mpsc::UnboundedSender<List>,
+}
+
+impl ListUpdater {
+    /// Replaces the list with a new one.
+    ///
+    /// Streamed lists are sent once they are complete.
+    pub fn set_list(&self, list: List) {
+        // The query has been replaced if the receiver is gone, just ignore.
+        _ = self.lists.send(list);
+    }
+
+    /// Whether the list is no longer being shown, so updates should stop.
+    pub fn is_closed(&self) -> bool {
+        self.lists.is_closed()
+    }
+
+    /// Waits until the Covey window is shown, or the list is closed.
+    ///
+    /// Updates aren't sent while the window is hidden, so wait for this
+    /// before doing the work for the next update.
+    pub async fn wait_until_shown(&self) {
+        let mut shown = WINDOW_SHOWN.subscribe();
+        tokio::select! {
+            _ = shown.wait_for(|shown| *shown) => {}
+            () = self.lists.closed() => {}
+        }
+    }
+}
+
+/// Whether the Covey window is shown.
+///
+/// Hosts without [`covey_proto::Feature::Lifecycle`] never say that it is
+/// hidden.
+static WINDOW_SHOWN: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::Sender::new(true));
+
+pub(crate) fn set_window_shown(shown: bool) {
+    WINDOW_SHOWN.send_replace(shown);
+}
+
+/// Resolves once the Covey window is shown.
+pub(crate) async fn window_shown() {
+    _ = WINDOW_SHOWN.subscribe().wait_for(|shown| *shown).await;
 }
 
 pub struct ListSection {
@@ -97,6 +300,15 @@ pub struct ListItem {
     pub title: String,
     pub description: String,
     pub icon: Option<Icon>,
+    pub preview: Option<Preview>,
+    /// Short text shown at the end of the item, like a shortcut or date.
+    pub accessory: Option<String>,
+    pub tags: Vec<Tag>,
+    /// Characters to emphasise. Set automatically by [`rank::rank`].
+    pub highlights: Highlights,
+    /// Identifies this item when the list is refreshed, so that the
+    /// selection can stay on it.
+    pub key: Option<String>,
     pub(crate) visit_id: VisitId,
     pub(crate) callbacks: TargetCallbacks,
 }
@@ -108,6 +320,11 @@ impl ListItem {
             title: title.clone(),
             icon: None,
             description: String::new(),
+            preview: None,
+            accessory: None,
+            tags: vec![],
+            highlights: Highlights::default(),
+            key: None,
             visit_id: VisitId::from(title),
             callbacks: TargetCallbacks::new(),
         }
@@ -119,6 +336,16 @@ impl ListItem {
         self
     }
 
+    /// Sets a key that stays the same when the list is refreshed.
+    ///
+    /// If the selected item has a key, the item with the same key in the next
+    /// list from this plugin will be selected.
+    #[must_use = "builder method consumes self"]
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
     #[must_use = "builder method consumes self"]
     pub fn with_icon(mut self, icon: Option<Icon>) -> Self {
         self.icon = icon;
@@ -137,6 +364,54 @@ impl ListItem {
         self
     }
 
+    #[must_use = "builder method consumes self"]
+    pub fn with_icon_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.icon = Some(Icon::File(path.into()));
+        self
+    }
+
+    #[must_use = "builder method consumes self"]
+    pub fn with_icon_data(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.icon = Some(Icon::Data(bytes.into()));
+        self
+    }
+
+    /// Content to show beside the list while this item is selected.
+    #[must_use = "builder method consumes self"]
+    pub fn with_preview(mut self, preview: Preview) -> Self {
+        self.preview = Some(preview);
+        self
+    }
+
+    /// Short text shown at the end of the item, like a shortcut, date or file
+    /// size.
+    #[must_use = "builder method consumes self"]
+    pub fn with_accessory(mut self, accessory: impl Into<String>) -> Self {
+        self.accessory = Some(accessory.into());
+        self
+    }
+
+    /// Adds a colored tag to the end of the item.
+    #[must_use = "builder method consumes self"]
+    pub fn with_tag(mut self, text: impl Into<String>, color: TagColor) -> Self {
+        self.tags.push(Tag {
+            text: text.into(),
+            color,
+        });
+        self
+    }
+
+    /// Characters of the title and description to emphasise.
+    ///
+    /// Items returned by [`rank::rank`] already have highlights set. Use
+    /// [`rank::highlights`] to compute these for items that are ranked
+    /// some other way.
+    #[must_use = "builder method consumes self"]
+    pub fn with_highlights(mut self, highlights: Highlights) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
     #[must_use = "builder method consumes self"]
     pub fn with_visit_id(mut self, id: impl Into<VisitId>) -> Self {
         self.visit_id = id.into();
@@ -195,11 +470,112 @@ impl ListItem {
             .add_callback(covey_proto::CommandId::new(name), callback);
         self
     }
+
+    /// Adds a command that can be called, shown with a different title.
+    ///
+    /// This should not be used directly, use the extension trait generated
+    /// by [`crate::include_manifest!`] instead.
+    #[doc(hidden)]
+    #[must_use]
+    pub fn add_command_with_title(
+        mut self,
+        name: &'static str,
+        title: impl Into<CommandTitle>,
+        callback: impl AsyncFn(&Menu) -> crate::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.callbacks.add_titled_callback(
+            covey_proto::CommandId::new(name),
+            title.into(),
+            callback,
+        );
+        self
+    }
+
+    /// Adds a command that isn't declared in the manifest.
+    ///
+    /// The command isn't shown if Covey doesn't support runtime commands.
+    #[must_use = "builder method consumes self"]
+    pub fn with_command(
+        mut self,
+        command: RuntimeCommand,
+        callback: impl AsyncFn(&Menu) -> crate::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.callbacks.add_runtime_callback(command.0, callback);
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum Icon {
+    /// A freedesktop icon name, looked up in the user's icon themes.
     Name(String),
     Text(String),
+    /// Absolute path to an image file.
+    File(PathBuf),
+    /// Contents of an image file, such as a PNG or SVG.
+    Data(Vec<u8>),
+    /// Uses the first icon that can be found.
+    ///
+    /// For example, `Icon::Fallback(vec![Icon::Name(..), Icon::Text(..)])`
+    /// will show the text if the named icon isn't in the user's icon theme.
+    ///
+    /// Versions of Covey that can't show files or data use the first name or
+    /// text icon.
+    Fallback(Vec<Icon>),
+}
+
+/// A colored label shown at the end of a list item, like "pinned" or
+/// "3 unread".
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub text: String,
+    pub color: TagColor,
+}
+
+/// Detailed content about a list item, shown while it is selected.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Preview {
+    /// Markdown text. Only headings, lists, code blocks and paragraphs are
+    /// guaranteed to be rendered.
+    Markdown(String),
+    /// Text shown in a monospace font.
+    Code {
+        text: String,
+        language: Option<String>,
+    },
+    /// Path to an image file, relative to the plugin's working directory if
+    /// it isn't absolute.
+    Image(PathBuf),
+    /// A table of key/value pairs.
+    Metadata(Vec<(String, String)>),
+}
+
+impl Preview {
+    pub fn markdown(text: impl Into<String>) -> Self {
+        Self::Markdown(text.into())
+    }
+
+    pub fn code(text: impl Into<String>, language: Option<String>) -> Self {
+        Self::Code {
+            text: text.into(),
+            language,
+        }
+    }
+
+    pub fn image(path: impl Into<PathBuf>) -> Self {
+        Self::Image(path.into())
+    }
+
+    pub fn metadata(
+        entries: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> Self {
+        Self::Metadata(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
 }
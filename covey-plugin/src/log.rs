@@ -0,0 +1,199 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{Layer, layer::Context};
+
+/// Whether the host can receive [`covey_proto::ResponseBody::Log`]. Set after
+/// the handshake.
+static HOST_SUPPORTS_LOGS: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_host_supports_logs(supported: bool) {
+    HOST_SUPPORTS_LOGS.store(supported, Ordering::Relaxed);
+}
+
+/// A [`tracing`] layer that sends events to Covey, which shows them in its
+/// own logs.
+///
+/// This is installed by [`run_server`](crate::run_server) unless a global
+/// subscriber has already been set. Add this layer to your own subscriber to
+/// keep sending logs to Covey.
+///
+/// Events are written to stderr instead if this version of Covey can't
+/// receive them.
+#[derive(Debug, Default)]
+pub struct LogLayer {
+    _private: (),
+}
+
+impl LogLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        match output(event) {
+            Output::Covey(record) => crate::transport::send(&covey_proto::Response::log(record)),
+            Output::Stderr(text) => eprintln!("{text}"),
+        }
+    }
+}
+
+/// Where an event is written to.
+#[derive(Debug, PartialEq)]
+enum Output {
+    Covey(covey_proto::LogRecord),
+    Stderr(String),
+}
+
+fn output(event: &Event<'_>) -> Output {
+    let mut visitor = FieldVisitor::default();
+    event.record(&mut visitor);
+
+    let metadata = event.metadata();
+    let record = covey_proto::LogRecord {
+        level: log_level(*metadata.level()),
+        target: metadata.target().to_owned(),
+        message: visitor.message,
+        fields: visitor.fields,
+    };
+
+    if HOST_SUPPORTS_LOGS.load(Ordering::Relaxed) {
+        Output::Covey(record)
+    } else {
+        Output::Stderr(plain_text(&record))
+    }
+}
+
+fn log_level(level: Level) -> covey_proto::LogLevel {
+    match level {
+        Level::TRACE => covey_proto::LogLevel::Trace,
+        Level::DEBUG => covey_proto::LogLevel::Debug,
+        Level::INFO => covey_proto::LogLevel::Info,
+        Level::WARN => covey_proto::LogLevel::Warn,
+        Level::ERROR => covey_proto::LogLevel::Error,
+    }
+}
+
+/// Formats the record like `WARN my_plugin: message key=value`.
+fn plain_text(record: &covey_proto::LogRecord) -> String {
+    let level = match record.level {
+        covey_proto::LogLevel::Trace => "TRACE",
+        covey_proto::LogLevel::Debug => "DEBUG",
+        covey_proto::LogLevel::Info => "INFO",
+        covey_proto::LogLevel::Warn => "WARN",
+        covey_proto::LogLevel::Error => "ERROR",
+    };
+    let mut text = format!("{level} {}: {}", record.target, record.message);
+    for (key, value) in &record.fields {
+        _ = write!(text, " {key}={value}");
+    }
+    text
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                serde_json::Value::String(message) => message,
+                other => other.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_owned(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::{
+        Layer,
+        layer::{Context, SubscriberExt as _},
+    };
+
+    use super::{Output, output, set_host_supports_logs};
+
+    /// Keeps the output of [`LogLayer`](super::LogLayer) instead of writing
+    /// it.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Output>>>);
+
+    impl<S: Subscriber> Layer<S> for Capture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(output(event));
+        }
+    }
+
+    fn log(capture: &Capture) {
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "my_plugin", ms = 120, path = "/tmp", "slow");
+        });
+    }
+
+    #[test]
+    fn fallback_to_stderr() {
+        let capture = Capture::default();
+
+        // Not set until the host says it supports logs.
+        log(&capture);
+        set_host_supports_logs(true);
+        log(&capture);
+        set_host_supports_logs(false);
+
+        let outputs = capture.0.lock().unwrap();
+        assert_eq!(
+            outputs[0],
+            Output::Stderr(String::from(r#"WARN my_plugin: slow ms=120 path="/tmp""#))
+        );
+        let Output::Covey(record) = &outputs[1] else {
+            panic!("expected a record for covey, got {:?}", outputs[1]);
+        };
+        assert_eq!(record.level, covey_proto::LogLevel::Warn);
+        assert_eq!(record.message, "slow");
+        assert_eq!(record.fields["ms"], serde_json::json!(120));
+    }
+}
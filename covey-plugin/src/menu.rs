@@ -1,11 +1,30 @@
-use std::fmt::Display;
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-use crate::{Action, Input};
+use crate::{
+    Action, ClipboardContent, Input, List, Notification,
+    prompt::{PendingPrompts, Prompt, PromptAnswer},
+    view::Views,
+};
 
 /// Provides methods to interact with the app menu.
 pub struct Menu {
     /// The request ID that all responses are replying to.
     pub(crate) request_id: covey_proto::RequestId,
+    /// The protocol version and features supported by the host.
+    pub(crate) host: Arc<covey_proto::Hello>,
+    pub(crate) prompts: PendingPrompts,
+    /// Whether progress is being shown, so that it can be ended once the
+    /// callback finishes.
+    pub(crate) showing_progress: Arc<AtomicBool>,
+    pub(crate) views: Views,
 }
 
 impl Menu {
@@ -14,7 +33,7 @@ impl Menu {
             self.request_id,
             crate::into_proto::action(action),
         );
-        println!("{}", response.serialize());
+        crate::transport::send(&response);
     }
 
     pub fn close(&self) {
@@ -25,6 +44,20 @@ impl Menu {
         self.send_action(Action::copy(str))
     }
 
+    /// Copies an image, HTML or files.
+    ///
+    /// If Covey can't copy the content, plain text is copied instead. Images
+    /// have no plain text, so an error is shown.
+    pub fn copy_content(&self, content: ClipboardContent) {
+        if self.host.supports(covey_proto::Feature::RichClipboard) {
+            self.send_action(Action::copy_content(content));
+        } else if let Some(text) = content.fallback_text() {
+            self.copy(text);
+        } else {
+            self.display_error("This version of Covey can't copy images");
+        }
+    }
+
     pub fn set_input(&self, input: impl Into<Input>) {
         self.send_action(Action::set_input(input))
     }
@@ -32,4 +65,167 @@ impl Menu {
     pub fn display_error(&self, err: impl Display) {
         self.send_action(Action::display_error(err))
     }
+
+    /// See [`Action::open_url`].
+    ///
+    /// If Covey doesn't have an opener, `xdg-open` is used instead.
+    pub fn open_url(&self, url: impl Into<String>) {
+        let url = url.into();
+        if self.host.supports(covey_proto::Feature::Opener) {
+            self.send_action(Action::open_url(url));
+        } else {
+            self.xdg_open(url);
+        }
+    }
+
+    /// See [`Action::open_path`].
+    ///
+    /// If Covey doesn't have an opener, `xdg-open` is used instead.
+    pub fn open_path(&self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        if self.host.supports(covey_proto::Feature::Opener) {
+            self.send_action(Action::open_path(path));
+        } else {
+            self.xdg_open(path);
+        }
+    }
+
+    fn xdg_open(&self, target: impl AsRef<OsStr>) {
+        if let Err(e) = crate::spawn::command("xdg-open", [target]) {
+            self.display_error(format!("failed to run xdg-open: {e}"));
+        }
+    }
+
+    /// See [`Action::notify`].
+    ///
+    /// If Covey can't show notifications, warnings and errors are shown with
+    /// [`Menu::display_error`] and other notifications are ignored.
+    pub fn notify(&self, notification: Notification) {
+        if self.host.supports(covey_proto::Feature::Notifications) {
+            self.send_action(Action::notify(notification));
+            return;
+        }
+
+        let covey_proto::Notification {
+            severity,
+            title,
+            body,
+            timeout_ms: _,
+        } = notification.0;
+        match severity {
+            covey_proto::Severity::Warning | covey_proto::Severity::Error if body.is_empty() => {
+                self.display_error(title);
+            }
+            covey_proto::Severity::Warning | covey_proto::Severity::Error => {
+                self.display_error(format!("{title}: {body}"));
+            }
+            covey_proto::Severity::Info | covey_proto::Severity::Success => {}
+        }
+    }
+
+    /// Asks the user a question and waits for their answer.
+    ///
+    /// Returns [`None`] if the user dismissed the prompt or Covey doesn't
+    /// support prompts.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// .on_rename(clone_async!(path, |menu| {
+    ///     let prompt = Prompt::text("Rename file").with_initial(file_name(&path));
+    ///     if let Some(PromptAnswer::Text(name)) = menu.prompt(prompt).await {
+    ///         fs::rename(&path, path.with_file_name(name))?;
+    ///     }
+    ///     Ok(())
+    /// }))
+    /// ```
+    pub async fn prompt(&self, prompt: Prompt) -> Option<PromptAnswer> {
+        if !self.host.supports(covey_proto::Feature::Prompts) {
+            return None;
+        }
+
+        let (id, answer) = self.prompts.register();
+        self.send_action(Action(covey_proto::PluginAction::Prompt(
+            covey_proto::Prompt {
+                id,
+                title: prompt.title,
+                kind: prompt.kind,
+            },
+        )));
+        answer.await.ok().flatten()
+    }
+
+    /// Shows progress of this command to the user.
+    ///
+    /// `fraction` should be between 0 and 1. The progress is hidden once the
+    /// callback returns, or with [`Menu::end_progress`].
+    ///
+    /// The user may cancel the command while it is running. See
+    /// [`Cancellation`](crate::Cancellation) for how to handle this.
+    ///
+    /// Does nothing if Covey doesn't support progress.
+    pub fn set_progress(&self, fraction: f32, message: impl Into<String>) {
+        self.send_progress(Some(fraction.clamp(0.0, 1.0)), message.into());
+    }
+
+    /// Shows that this command is running without knowing how far through
+    /// it is.
+    ///
+    /// See [`Menu::set_progress`].
+    pub fn set_indeterminate_progress(&self, message: impl Into<String>) {
+        self.send_progress(None, message.into());
+    }
+
+    /// Hides progress shown by [`Menu::set_progress`].
+    pub fn end_progress(&self) {
+        if self.showing_progress.swap(false, Ordering::Relaxed) {
+            self.send_action(Action(covey_proto::PluginAction::EndProgress));
+        }
+    }
+
+    fn send_progress(&self, fraction: Option<f32>, message: String) {
+        if !self.host.supports(covey_proto::Feature::Progress) {
+            return;
+        }
+
+        self.showing_progress.store(true, Ordering::Relaxed);
+        self.send_action(Action(covey_proto::PluginAction::Progress(
+            covey_proto::Progress { fraction, message },
+        )));
+    }
+
+    /// Shows a new list on top of the current one, like opening a folder.
+    ///
+    /// The input is cleared and every query is sent to `query` instead of
+    /// [`Plugin::query`](crate::Plugin::query) until the user goes back.
+    ///
+    /// Does nothing if Covey doesn't support views.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// .on_activate(clone_async!(folder, |menu| {
+    ///     menu.push_view(folder.name.clone(), clone_async!(folder, |query| {
+    ///         let items = folder.bookmark_items();
+    ///         Ok(List::new(rank::rank(&query, &items, Weights::default()).await))
+    ///     }));
+    ///     Ok(())
+    /// }))
+    /// ```
+    pub fn push_view(
+        &self,
+        title: impl Into<String>,
+        query: impl AsyncFn(String) -> crate::Result<List> + Send + Sync + 'static,
+    ) {
+        if !self.host.supports(covey_proto::Feature::Views) {
+            eprintln!("covey does not support views");
+            return;
+        }
+
+        let id = self.views.insert(query);
+        self.send_action(Action(covey_proto::PluginAction::PushView(
+            covey_proto::View {
+                id,
+                title: title.into(),
+            },
+        )));
+    }
 }
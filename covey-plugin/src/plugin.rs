@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::{List, Result, manifest::ManifestDeserialization};
+use crate::{Cancellation, List, QueryContext, Result, manifest::ManifestDeserialization};
 
 pub trait Plugin: Sized + 'static {
     /// The user's configuration for this plugin.
@@ -11,8 +11,49 @@ pub trait Plugin: Sized + 'static {
     #[expect(async_fn_in_trait, reason = "plugin is single threaded")]
     async fn new(config: Self::Config) -> Result<Self>;
 
+    /// Use [`QueryContext::current`] to get more information about the query,
+    /// like the cursor position.
     #[expect(async_fn_in_trait, reason = "plugin is single threaded")]
     async fn query(&self, query: String) -> Result<List>;
+
+    /// Applies a new configuration after the user changed some settings.
+    ///
+    /// Return `Ok(false)` to replace this plugin with a new one from
+    /// [`Plugin::new`] instead, which is what happens by default. Implement
+    /// this to keep state that is expensive to rebuild.
+    ///
+    /// This isn't called again until the previous call finishes. If the
+    /// settings change several times meanwhile, only the latest are applied.
+    #[expect(async_fn_in_trait, reason = "plugin is single threaded")]
+    async fn reconfigure(&self, config: Self::Config) -> Result<bool> {
+        _ = config;
+        Ok(false)
+    }
+
+    /// Called when the launcher window is opened while this plugin is
+    /// running.
+    #[expect(async_fn_in_trait, reason = "plugin is single threaded")]
+    async fn window_shown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when the launcher window is closed.
+    ///
+    /// Updates to live lists are held back until the window is shown again,
+    /// see [`ListUpdater::wait_until_shown`](crate::ListUpdater::wait_until_shown).
+    #[expect(async_fn_in_trait, reason = "plugin is single threaded")]
+    async fn window_hidden(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before the plugin exits, to save any state.
+    ///
+    /// The plugin is killed if this and any writes from
+    /// [`Visits`](crate::rank::Visits) take more than a couple of seconds.
+    #[expect(async_fn_in_trait, reason = "plugin is single threaded")]
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Private to not expose that [`Arc<Plugin>`] can implement [`Plugin`].
@@ -27,10 +68,32 @@ impl<T: Plugin + Send + Sync> Plugin for BlockingPluginWrapper<T> {
 
     async fn query(&self, query: String) -> Result<List> {
         let this = Arc::clone(&self.0);
+        let cancellation = Cancellation::current();
+        let context = QueryContext::current();
         tokio::task::spawn_blocking(move || {
-            tokio::runtime::Handle::current().block_on(T::query(&this, query))
+            cancellation.sync_scope(|| {
+                context.sync_scope(|| {
+                    tokio::runtime::Handle::current().block_on(T::query(&this, query))
+                })
+            })
         })
         .await
         .unwrap()
     }
+
+    async fn reconfigure(&self, config: Self::Config) -> Result<bool> {
+        self.0.reconfigure(config).await
+    }
+
+    async fn window_shown(&self) -> Result<()> {
+        self.0.window_shown().await
+    }
+
+    async fn window_hidden(&self) -> Result<()> {
+        self.0.window_hidden().await
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        self.0.shutdown().await
+    }
 }
@@ -0,0 +1,100 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+pub use covey_proto::PromptAnswer;
+use tokio::sync::oneshot;
+
+/// A question to ask the user, shown with
+/// [`Menu::prompt`](crate::Menu::prompt).
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub(crate) title: String,
+    pub(crate) kind: covey_proto::PromptKind,
+}
+
+impl Prompt {
+    /// Asks for a single line of text, answered with [`PromptAnswer::Text`].
+    pub fn text(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            kind: covey_proto::PromptKind::Text {
+                placeholder: String::new(),
+                initial: String::new(),
+            },
+        }
+    }
+
+    /// Asks to choose one of `options`, answered with the index of the option
+    /// in [`PromptAnswer::Choice`].
+    pub fn choice(
+        title: impl Into<String>,
+        options: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            kind: covey_proto::PromptKind::Choice {
+                options: options.into_iter().map(Into::into).collect(),
+            },
+        }
+    }
+
+    /// Asks a yes/no question, answered with [`PromptAnswer::Confirm`].
+    pub fn confirm(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            kind: covey_proto::PromptKind::Confirm,
+        }
+    }
+
+    /// Only applies to [`Prompt::text`].
+    #[must_use = "builder method consumes self"]
+    pub fn with_placeholder(mut self, text: impl Into<String>) -> Self {
+        if let covey_proto::PromptKind::Text { placeholder, .. } = &mut self.kind {
+            *placeholder = text.into();
+        }
+        self
+    }
+
+    /// Only applies to [`Prompt::text`].
+    #[must_use = "builder method consumes self"]
+    pub fn with_initial(mut self, text: impl Into<String>) -> Self {
+        if let covey_proto::PromptKind::Text { initial, .. } = &mut self.kind {
+            *initial = text.into();
+        }
+        self
+    }
+}
+
+type AnswerReceiver = oneshot::Receiver<Option<PromptAnswer>>;
+
+/// Prompts that are waiting for an answer from the host.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PendingPrompts(Arc<Mutex<PendingPromptsInner>>);
+
+#[derive(Debug, Default)]
+struct PendingPromptsInner {
+    next_id: u64,
+    senders: BTreeMap<covey_proto::PromptId, oneshot::Sender<Option<PromptAnswer>>>,
+}
+
+impl PendingPrompts {
+    pub(crate) fn register(&self) -> (covey_proto::PromptId, AnswerReceiver) {
+        let (tx, rx) = oneshot::channel();
+        let mut inner = self.0.lock().unwrap();
+        let id = covey_proto::PromptId(inner.next_id);
+        inner.next_id += 1;
+        inner.senders.insert(id, tx);
+        (id, rx)
+    }
+
+    pub(crate) fn answer(&self, id: covey_proto::PromptId, answer: Option<PromptAnswer>) {
+        let sender = self.0.lock().unwrap().senders.remove(&id);
+        match sender {
+            // The callback may have been dropped while waiting.
+            Some(sender) => _ = sender.send(answer),
+            None => eprintln!("received answer to unknown prompt {id:?}"),
+        }
+    }
+}
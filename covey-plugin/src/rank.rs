@@ -13,6 +13,7 @@ use std::{
     collections::HashMap,
     hash::BuildHasher,
     io::Read,
+    ops::Range,
     path::PathBuf,
     sync::LazyLock,
     time::{Duration, SystemTime},
@@ -190,6 +191,50 @@ pub(crate) fn accuracy(query: &str, item: &ListItem, weights: Weights) -> f32 {
     title_score as f32 * weights.title + desc_score as f32 * weights.description
 }
 
+/// Character ranges of a list item that match a query.
+///
+/// Frontends will show these characters in a different color.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Highlights {
+    pub title: Vec<Range<usize>>,
+    pub description: Vec<Range<usize>>,
+}
+
+/// Finds the characters of the list item that match the query.
+///
+/// Only fields with a non-zero weight are matched, the same as
+/// [`ListItem::accuracy`](crate::ListItem::accuracy).
+pub fn highlights(query: &str, item: &ListItem, weights: Weights) -> Highlights {
+    accuracy_with_highlights(query, item, weights).1
+}
+
+/// Same as [`accuracy`], but also finds the characters that matched.
+fn accuracy_with_highlights(query: &str, item: &ListItem, weights: Weights) -> (f32, Highlights) {
+    let match_field = |weight: f32, text: &str| {
+        (weight != 0.0)
+            .then(|| MATCHER.fuzzy_indices(text, query))
+            .flatten()
+            .map(|(score, indices)| (score as f32 * weight, indices_to_ranges(indices)))
+            .unwrap_or_default()
+    };
+
+    let (title_score, title) = match_field(weights.title, &item.title);
+    let (desc_score, description) = match_field(weights.description, &item.description);
+    (title_score + desc_score, Highlights { title, description })
+}
+
+/// Merges sorted character indices into ranges of consecutive characters.
+fn indices_to_ranges(indices: impl IntoIterator<Item = usize>) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for i in indices {
+        match ranges.last_mut() {
+            Some(last) if last.end == i => last.end += 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
 pub(crate) fn frecency(
     item: &ListItem,
     visits: &Visits,
@@ -231,33 +276,41 @@ impl Frecency {
 /// Ranks a slice of list items by the given weights in descending order of
 /// score.
 ///
-/// Returns up to 100 items that have a score of at least 1.
+/// Returns up to 100 items that have a score of at least 1. The returned
+/// items will have their [`Highlights`] set to the characters that matched the
+/// query.
 #[expect(clippy::unused_async, reason = "may require async in the future")]
 pub async fn rank(query: &str, items: &[ListItem], weights: Weights) -> Vec<ListItem> {
-    let mut scored: Vec<_> = if weights.frecency == 0.0 {
-        items
-            .iter()
-            .map(|item| (item, item.accuracy(query, weights)))
-            .filter(|(_, score)| query.is_empty() || *score > 1.0)
-            .collect()
-    } else {
-        let visits = Visits::from_file();
-        let now = SystemTime::now();
-
-        items
-            .iter()
-            .map(|item| (item, item.score(query, &visits, now, weights)))
-            .filter(|(_, score)| query.is_empty() || *score > 1.0)
-            .collect()
-    };
+    let visits = (weights.frecency != 0.0).then(Visits::from_file);
+    let now = SystemTime::now();
+
+    let mut scored: Vec<_> = items
+        .iter()
+        .map(|item| {
+            let (accuracy, highlights) = accuracy_with_highlights(query, item, weights);
+            let score = match &visits {
+                Some(visits) => {
+                    frecency(item, visits, now, weights).combine_with_accuracy(accuracy)
+                }
+                None => accuracy,
+            };
+            (item, score, highlights)
+        })
+        .filter(|(_, score, _)| query.is_empty() || *score > 1.0)
+        .collect();
 
     // reverse order
-    scored.sort_unstable_by(|(_, s1), (_, s2)| s2.total_cmp(s1));
+    scored.sort_unstable_by(|(_, s1, _), (_, s2, _)| s2.total_cmp(s1));
     scored
         .into_iter()
-        .map(|(item, _)| item)
         .take(100)
-        .cloned()
+        .map(|(item, _, highlights)| {
+            let mut item = item.clone();
+            if !query.is_empty() {
+                item.highlights = highlights;
+            }
+            item
+        })
         .collect()
 }
 
@@ -301,3 +354,29 @@ impl Weights {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Weights, accuracy, accuracy_with_highlights, indices_to_ranges};
+    use crate::ListItem;
+
+    #[test]
+    fn merge_consecutive_indices() {
+        assert!(indices_to_ranges([]).is_empty());
+        assert_eq!(indices_to_ranges([3]), vec![3..4]);
+        assert_eq!(
+            indices_to_ranges([0, 1, 2, 5, 7, 8]),
+            vec![0..3, 5..6, 7..9]
+        );
+    }
+
+    #[test]
+    fn highlights_match_accuracy() {
+        let item = ListItem::new("Open settings").with_description("Change preferences");
+        let weights = Weights::without_history();
+
+        let (score, highlights) = accuracy_with_highlights("ose", &item, weights);
+        assert!((score - accuracy("ose", &item, weights)).abs() < f32::EPSILON);
+        assert_eq!(highlights.title, vec![0..1, 5..7]);
+    }
+}
@@ -1,11 +1,26 @@
-use std::{process, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    ops::ControlFlow,
+    process,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 use anyhow::Context;
-use tokio::{io::AsyncBufReadExt as _, task::LocalSet};
+use tokio::{
+    sync::mpsc,
+    task::{AbortHandle, LocalSet},
+};
+use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _};
 
 use crate::{
-    Plugin, manifest::ManifestDeserialization as _, plugin::BlockingPluginWrapper,
-    store::CommandMap,
+    Cancellation, Environment, List, LogLayer, Plugin, QueryContext,
+    manifest::ManifestDeserialization as _, plugin::BlockingPluginWrapper, prompt::PendingPrompts,
+    store::CommandMap, transport::Requests, view::Views,
 };
 
 /// Starts up the server with a specified plugin implementation.
@@ -68,6 +83,10 @@ pub fn run_server<T: Plugin>(plugin_id: &'static str) -> ! {
     crate::PLUGIN_ID
         .set(plugin_id)
         .expect("plugin id should only be set from main");
+    // The plugin may have set up its own logging.
+    _ = tracing_subscriber::registry()
+        .with(LogLayer::new())
+        .try_init();
 
     let result = tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -82,7 +101,7 @@ pub fn run_server<T: Plugin>(plugin_id: &'static str) -> ! {
     match result {
         Ok(()) => process::exit(0),
         Err(e) => {
-            println!("{e:#}");
+            eprintln!("{e:#}");
             process::exit(1)
         }
     }
@@ -102,76 +121,499 @@ pub fn run_server_blocking<T: Plugin + Send + Sync>(plugin_id: &'static str) ->
 }
 
 async fn main<T: Plugin>() -> anyhow::Result<()> {
-    let manifest_json = std::env::args()
-        .nth(1)
-        .context("JSON manifest must be provided as the first argument to this plugin")?;
-    let plugin = T::new(T::Config::try_from_input(&manifest_json)?).await?;
-    let plugin = Arc::new(plugin);
+    let mut requests = Requests::connect();
+
+    // Covey waits for the plugin's hello before sending anything.
+    let hello = covey_proto::Hello::current();
+    crate::transport::send(&covey_proto::Response::hello(hello.clone()));
 
-    let command_map = CommandMap::new();
+    // The first line should be the host's hello. Hosts without a handshake
+    // use an older protocol version, and would silently drop every response.
+    let Some(first_line) = requests.next_line().await? else {
+        eprintln!("covey closed the connection");
+        return Ok(());
+    };
+    let Ok(covey_proto::Request {
+        request: covey_proto::RequestBody::Hello(host),
+        ..
+    }) = serde_json::from_str(&first_line)
+    else {
+        anyhow::bail!(
+            "covey uses an older protocol version, but this plugin uses protocol version {}",
+            hello.protocol_version
+        );
+    };
+    // Covey refuses this plugin as well, but exit instead of waiting to be
+    // killed.
+    if !hello.is_compatible_with(&host) {
+        anyhow::bail!(
+            "covey uses protocol version {}, but this plugin uses protocol version {}",
+            host.protocol_version,
+            hello.protocol_version
+        );
+    }
+
+    crate::log::set_host_supports_logs(host.supports(covey_proto::Feature::Logs));
+    crate::storage::set_host_supports_storage(host.supports(covey_proto::Feature::Storage));
 
-    let mut stdin_lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    let line = requests
+        .next_line()
+        .await?
+        .context("covey closed the connection before sending the plugin settings")?;
+    let covey_proto::Request {
+        request: covey_proto::RequestBody::Initialize(initialize),
+        ..
+    } = serde_json::from_str(&line).context("malformed request from covey")?
+    else {
+        anyhow::bail!("expected covey to send the plugin settings, got {line}");
+    };
+    let settings = initialize.settings.clone();
+    Environment::set(initialize);
+
+    // The plugin may use storage while it is being created, so storage
+    // results need to be handled before the server starts.
+    let mut requests = spawn_reader(requests);
+
+    let plugin = T::new(parse_config::<T>(&settings)?).await?;
+    let host = Arc::new(host);
+    let (configure_tx, configure_rx) = mpsc::unbounded_channel();
+    let server = Server {
+        plugin: Rc::new(RefCell::new(Arc::new(plugin))),
+        settings: Rc::new(RefCell::new(settings)),
+        configures: configure_tx,
+        command_map: CommandMap::new(Arc::clone(&host)),
+        host,
+        tasks: Rc::default(),
+        prompts: PendingPrompts::default(),
+        views: Views::default(),
+    };
+    tokio::task::spawn_local(server.clone().apply_configures(configure_rx));
 
     loop {
-        match stdin_lines.next_line().await {
-            Err(e) => anyhow::bail!(e),
+        match requests.recv().await {
             // No more lines
-            Ok(None) => {
-                eprintln!("stdin closed");
+            None => {
+                eprintln!("covey closed the connection");
                 return Ok(());
             }
-            Ok(Some(line)) => handle_request_line(Arc::clone(&plugin), command_map.clone(), &line)?,
+            Some(request) => {
+                if server.handle_request(request?).is_break() {
+                    return server.shutdown().await;
+                }
+            }
         }
     }
 }
 
-fn handle_request_line<T: Plugin>(
-    plugin: Arc<T>,
+/// Reads requests in a separate task, handling storage results immediately.
+fn spawn_reader(
+    mut requests: Requests,
+) -> mpsc::UnboundedReceiver<anyhow::Result<covey_proto::Request>> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::task::spawn_local(async move {
+        loop {
+            let request = match requests.next_line().await {
+                Ok(Some(line)) => parse_request(&line),
+                Ok(None) => return,
+                Err(e) => Err(e.into()),
+            };
+            let is_err = request.is_err();
+            match request {
+                Ok(covey_proto::Request {
+                    request: covey_proto::RequestBody::StorageResult(result),
+                    ..
+                }) => crate::storage::answer(result),
+                request => {
+                    if tx.send(request).is_err() || is_err {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    rx
+}
+
+fn parse_request(line: &str) -> anyhow::Result<covey_proto::Request> {
+    serde_json::from_str(line).context("malformed request from covey")
+}
+
+/// State shared between all requests.
+struct Server<T> {
+    /// Replaced if the plugin can't be reconfigured.
+    plugin: Rc<RefCell<Arc<T>>>,
+    /// The user's current settings for this plugin.
+    settings: Rc<RefCell<serde_json::Map<String, serde_json::Value>>>,
+    /// Settings to reconfigure the plugin with, applied in order by
+    /// [`Server::apply_configures`].
+    configures: mpsc::UnboundedSender<serde_json::Map<String, serde_json::Value>>,
     command_map: CommandMap,
-    line: &str,
-) -> anyhow::Result<()> {
-    let covey_proto::Request {
-        id: request_id,
-        request,
-    } = serde_json::from_str(line).context("malformed request from covey")?;
+    /// The protocol version and features supported by the host.
+    host: Arc<covey_proto::Hello>,
+    /// Requests that are still being handled.
+    tasks: Rc<RefCell<BTreeMap<covey_proto::RequestId, (AbortHandle, Cancellation)>>>,
+    /// Prompts sent by callbacks that are waiting for an answer.
+    prompts: PendingPrompts,
+    /// Views pushed by callbacks that are still open.
+    views: Views,
+}
+
+impl<T> Clone for Server<T> {
+    fn clone(&self) -> Self {
+        Self {
+            plugin: Rc::clone(&self.plugin),
+            settings: Rc::clone(&self.settings),
+            configures: self.configures.clone(),
+            command_map: self.command_map.clone(),
+            host: Arc::clone(&self.host),
+            tasks: Rc::clone(&self.tasks),
+            prompts: self.prompts.clone(),
+            views: self.views.clone(),
+        }
+    }
+}
+
+impl<T: Plugin> Server<T> {
+    /// Breaks if the plugin should shut down.
+    fn handle_request(&self, request: covey_proto::Request) -> ControlFlow<()> {
+        let covey_proto::Request {
+            id: request_id,
+            request,
+        } = request;
 
-    // Handling the request may take some time, don't block!
-    // Allow handling multiple requests at once by spawning a new task.
-    tokio::task::spawn_local(async move {
         match request {
-            covey_proto::RequestBody::Query(query) => {
-                match plugin.query(query.text).await {
-                    Ok(list) => {
-                        let proto_list = command_map.store_query_result(list);
-                        let response = covey_proto::Response::set_list(request_id, proto_list);
-                        println!("{}", response.serialize());
-                    }
-                    Err(e) => {
-                        let response =
-                            covey_proto::Response::display_error(request_id, format!("{e:#}"));
-                        println!("{}", response.serialize());
+            covey_proto::RequestBody::Hello(_) => {
+                eprintln!("ignoring repeated hello from covey");
+            }
+            covey_proto::RequestBody::Initialize(_) => {
+                eprintln!("ignoring repeated initialize from covey");
+            }
+            covey_proto::RequestBody::Cancel(covey_proto::RequestCancel { request_id }) => {
+                // Requests that have already finished will not be found.
+                if let Some((abort_handle, cancellation)) =
+                    self.tasks.borrow_mut().remove(&request_id)
+                {
+                    cancellation.cancel();
+                    abort_handle.abort();
+                }
+            }
+            covey_proto::RequestBody::PromptAnswer(covey_proto::RequestPromptAnswer {
+                prompt_id,
+                answer,
+            }) => self.prompts.answer(prompt_id, answer),
+            covey_proto::RequestBody::CloseView(covey_proto::RequestCloseView { view_id }) => {
+                self.views.remove(view_id);
+            }
+            covey_proto::RequestBody::StorageResult(result) => crate::storage::answer(result),
+            covey_proto::RequestBody::Lifecycle(covey_proto::LifecycleEvent::Shutdown) => {
+                return ControlFlow::Break(());
+            }
+            covey_proto::RequestBody::Lifecycle(
+                event @ (covey_proto::LifecycleEvent::WindowShown
+                | covey_proto::LifecycleEvent::WindowHidden),
+            ) => {
+                crate::list::set_window_shown(event == covey_proto::LifecycleEvent::WindowShown);
+                let plugin = self.plugin();
+                tokio::task::spawn_local(async move {
+                    let result = match event {
+                        covey_proto::LifecycleEvent::WindowShown => plugin.window_shown().await,
+                        _ => plugin.window_hidden().await,
+                    };
+                    if let Err(e) = result {
+                        eprintln!("failed to handle {event:?}: {e:#}");
                     }
+                });
+            }
+            covey_proto::RequestBody::Configure(covey_proto::RequestConfigure {
+                changed,
+                removed,
+            }) => {
+                let settings = {
+                    let mut settings = self.settings.borrow_mut();
+                    settings.extend(changed);
+                    settings.retain(|key, _| !removed.contains(key));
+                    settings.clone()
                 };
+                // Only fails if the plugin is shutting down.
+                _ = self.configures.send(settings);
+            }
+            covey_proto::RequestBody::Query(covey_proto::RequestQuery {
+                text,
+                view,
+                context,
+            }) => {
+                let this = self.clone();
+                let context =
+                    QueryContext::new(context.unwrap_or_else(|| covey_proto::QueryContext {
+                        raw: text.clone(),
+                        ..Default::default()
+                    }));
+                self.spawn_request(request_id, async move {
+                    let result = context
+                        .scope(async {
+                            match view {
+                                Some(view_id) => match this.views.get(view_id) {
+                                    Some(query) => query(text).await,
+                                    None => {
+                                        Err(anyhow::anyhow!("view {view_id:?} has been closed"))
+                                    }
+                                },
+                                None => this.plugin().query(text).await,
+                            }
+                        })
+                        .await;
+                    match result {
+                        Ok(list) => this.send_list(request_id, list).await,
+                        Err(e) => {
+                            let response =
+                                covey_proto::Response::display_error(request_id, format!("{e:#}"));
+                            crate::transport::send(&response);
+                        }
+                    };
+                });
             }
             covey_proto::RequestBody::Activate(covey_proto::RequestActivate {
                 target_id,
                 command_id,
             }) => {
-                let callback = command_map.find_callback(target_id, &command_id);
-
-                match callback {
-                    Some((visit_id, callback)) => {
-                        if let Some(visit_id) = visit_id {
-                            crate::rank::Visits::update_file_with_visit(visit_id)
-                        };
-                        callback(crate::Menu { request_id }).await;
-                    }
-                    None => {
-                        eprintln!("failed to fetch {command_id:?} of {target_id:?}")
+                let this = self.clone();
+                self.spawn_request(request_id, async move {
+                    let showing_progress = Arc::default();
+                    this.activate(request_id, &showing_progress, target_id, &command_id)
+                        .await;
+                    end_progress(request_id, &showing_progress);
+                });
+            }
+            covey_proto::RequestBody::ActivateMany(covey_proto::RequestActivateMany {
+                target_ids,
+                command_id,
+            }) => {
+                let this = self.clone();
+                self.spawn_request(request_id, async move {
+                    let showing_progress = Arc::default();
+                    let unkeyed = match this
+                        .command_map
+                        .find_batch_callback(&target_ids, &command_id)
+                    {
+                        Some(batch) => {
+                            for visit_id in batch.visit_ids {
+                                crate::rank::Visits::update_file_with_visit(visit_id);
+                            }
+                            if !batch.keys.is_empty() {
+                                let menu = this.menu(request_id, &showing_progress);
+                                (batch.callback)(menu, batch.keys).await;
+                            }
+                            batch.unkeyed
+                        }
+                        None => target_ids,
+                    };
+                    for target_id in unkeyed {
+                        this.activate(request_id, &showing_progress, target_id, &command_id)
+                            .await;
                     }
+                    end_progress(request_id, &showing_progress);
+                });
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Runs the plugin's shutdown hook.
+    ///
+    /// Blocking tasks, like writing visits, are waited for when the runtime
+    /// is dropped after this returns.
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        for (abort_handle, cancellation) in
+            std::mem::take(&mut *self.tasks.borrow_mut()).into_values()
+        {
+            cancellation.cancel();
+            abort_handle.abort();
+        }
+        self.plugin().shutdown().await
+    }
+
+    fn plugin(&self) -> Arc<T> {
+        Arc::clone(&self.plugin.borrow())
+    }
+
+    /// Reconfigures the plugin with each settings sent to
+    /// [`Server::configures`], one at a time so that they can't be applied
+    /// out of order.
+    ///
+    /// Settings that were sent while the plugin was being reconfigured are
+    /// skipped, except for the latest.
+    async fn apply_configures(
+        self,
+        mut configures: mpsc::UnboundedReceiver<serde_json::Map<String, serde_json::Value>>,
+    ) {
+        while let Some(mut settings) = configures.recv().await {
+            while let Ok(newer) = configures.try_recv() {
+                settings = newer;
+            }
+            if let Err(e) = self.reconfigure(&settings).await {
+                let response = covey_proto::Response::display_error(
+                    covey_proto::RequestId::CONTROL,
+                    format!("{e:#}"),
+                );
+                crate::transport::send(&response);
+            }
+        }
+    }
+
+    /// Gives new settings to the plugin, replacing it if it can't be
+    /// reconfigured.
+    async fn reconfigure(
+        &self,
+        settings: &serde_json::Map<String, serde_json::Value>,
+    ) -> anyhow::Result<()> {
+        let plugin = self.plugin();
+        if !plugin.reconfigure(parse_config::<T>(settings)?).await? {
+            let new_plugin = T::new(parse_config::<T>(settings)?).await?;
+            *self.plugin.borrow_mut() = Arc::new(new_plugin);
+        }
+        Ok(())
+    }
+
+    fn menu(
+        &self,
+        request_id: covey_proto::RequestId,
+        showing_progress: &Arc<AtomicBool>,
+    ) -> crate::Menu {
+        crate::Menu {
+            request_id,
+            host: Arc::clone(&self.host),
+            prompts: self.prompts.clone(),
+            showing_progress: Arc::clone(showing_progress),
+            views: self.views.clone(),
+        }
+    }
+
+    /// Runs the callback of a single target.
+    async fn activate(
+        &self,
+        request_id: covey_proto::RequestId,
+        showing_progress: &Arc<AtomicBool>,
+        target_id: covey_proto::ActivationTarget,
+        command_id: &covey_proto::CommandId,
+    ) {
+        match self.command_map.find_callback(target_id, command_id) {
+            Some((visit_id, callback)) => {
+                if let Some(visit_id) = visit_id {
+                    crate::rank::Visits::update_file_with_visit(visit_id)
                 };
+                callback(self.menu(request_id, showing_progress)).await;
+            }
+            None => {
+                eprintln!("failed to fetch {command_id:?} of {target_id:?}")
             }
         }
-    });
-    Ok(())
+    }
+
+    /// Handles a request in a new task so that it can be cancelled.
+    ///
+    /// Handling the request may take some time, don't block! This allows
+    /// handling multiple requests at once.
+    fn spawn_request(
+        &self,
+        request_id: covey_proto::RequestId,
+        fut: impl Future<Output = ()> + 'static,
+    ) {
+        let cancellation = Cancellation::default();
+        let tasks = Rc::clone(&self.tasks);
+        let handle = tokio::task::spawn_local(cancellation.clone().scope(async move {
+            fut.await;
+            tasks.borrow_mut().remove(&request_id);
+        }));
+        // The task won't start until this function returns, so this is
+        // inserted before it can be removed.
+        self.tasks
+            .borrow_mut()
+            .insert(request_id, (handle.abort_handle(), cancellation));
+    }
+
+    async fn send_list(&self, request_id: covey_proto::RequestId, mut list: List) {
+        let updates = list.updates.take();
+        self.send_query_result(request_id, list).await;
+
+        // Keep sending updates until the list is replaced by a newer query,
+        // which cancels this request.
+        let Some(mut updates) = updates else { return };
+        if !self.host.supports(covey_proto::Feature::ListUpdates) {
+            return;
+        }
+        while let Some(mut list) = updates.recv().await {
+            // Only the latest list made while the window was hidden is sent
+            // once it is shown again.
+            crate::list::window_shown().await;
+            while let Ok(newer) = updates.try_recv() {
+                list = newer;
+            }
+            if let Some(mut sections) = list.stream.take() {
+                while let Some(section) = sections.recv().await {
+                    list.push_section(section);
+                }
+            }
+            let proto_list = self.command_map.store_query_result(list);
+            let response = covey_proto::Response::update_list(request_id, proto_list);
+            crate::transport::send(&response);
+        }
+    }
+
+    async fn send_query_result(&self, request_id: covey_proto::RequestId, mut list: List) {
+        let Some(mut sections) = list.stream.take() else {
+            let proto_list = self.command_map.store_query_result(list);
+            let response = covey_proto::Response::set_list(request_id, proto_list);
+            crate::transport::send(&response);
+            return;
+        };
+
+        // Hosts that can't show partial lists get everything at once.
+        if !self.host.supports(covey_proto::Feature::StreamingLists) {
+            while let Some(section) = sections.recv().await {
+                list.push_section(section);
+            }
+            let proto_list = self.command_map.store_query_result(list);
+            let response = covey_proto::Response::set_list(request_id, proto_list);
+            crate::transport::send(&response);
+            return;
+        }
+
+        let proto_list = self.command_map.store_query_result(list);
+        let list_target_id = proto_list.id;
+        let response = covey_proto::Response::set_partial_list(request_id, proto_list);
+        crate::transport::send(&response);
+
+        while let Some(section) = sections.recv().await {
+            let Some(extension) = self
+                .command_map
+                .extend_query_result(list_target_id, section)
+            else {
+                // List has been replaced by a newer query.
+                return;
+            };
+            let response = covey_proto::Response::extend_list(request_id, extension);
+            crate::transport::send(&response);
+        }
+
+        let response = covey_proto::Response::finish_list(request_id);
+        crate::transport::send(&response);
+    }
+}
+
+fn parse_config<T: Plugin>(
+    settings: &serde_json::Map<String, serde_json::Value>,
+) -> anyhow::Result<T::Config> {
+    let settings_json = serde_json::to_string(settings)?;
+    Ok(T::Config::try_from_input(&settings_json)?)
+}
+
+/// Ends progress that a callback didn't end itself.
+fn end_progress(request_id: covey_proto::RequestId, showing_progress: &AtomicBool) {
+    if showing_progress.load(Ordering::Relaxed) {
+        let response = covey_proto::Response::perform_action(
+            request_id,
+            covey_proto::PluginAction::EndProgress,
+        );
+        crate::transport::send(&response);
+    }
 }
@@ -0,0 +1,178 @@
+//! Key/value storage managed by Covey.
+//!
+//! Prefer this over writing files in
+//! [`plugin_data_dir`](crate::plugin_data_dir). Covey keeps the storage of all
+//! plugins in one database, and each change is written in a transaction, so it
+//! can't be left half-written.
+//!
+//! Values are stored as JSON, so they can be read back as a different type
+//! as long as it deserializes from the same JSON.
+
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::{Context as _, bail};
+use covey_proto::{StorageId, StorageOperation, StorageResult};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::oneshot;
+
+use crate::Result;
+
+/// Values of type `T`, with keys in their own namespace.
+///
+/// # Examples
+/// ```ignore
+/// let bookmarks = Store::<Bookmark>::new("bookmarks");
+/// bookmarks.set("covey", &Bookmark { url }).await?;
+/// let all_bookmarks = bookmarks.list("").await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Store<T> {
+    namespace: String,
+    _values: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Store<T> {
+    /// Keys are stored as `<namespace>/<key>`, so different stores of the
+    /// same plugin won't conflict.
+    ///
+    /// # Panics
+    /// If the namespace contains a `/`, as the keys of another store could
+    /// then start with this namespace.
+    pub fn new(namespace: impl Into<String>) -> Self {
+        let namespace = namespace.into();
+        assert!(
+            !namespace.contains('/'),
+            "storage namespace {namespace:?} should not contain '/'"
+        );
+        Self {
+            namespace,
+            _values: PhantomData,
+        }
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}/{key}", self.namespace)
+    }
+
+    /// Returns [`None`] if the key isn't set.
+    pub async fn get(&self, key: &str) -> Result<Option<T>> {
+        let key = self.full_key(key);
+        match request(StorageOperation::Get { key }).await? {
+            StorageResult::Value(Some(value)) => Ok(Some(serde_json::from_value(value)?)),
+            StorageResult::Value(None) => Ok(None),
+            other => bail!("unexpected storage result {other:?}"),
+        }
+    }
+
+    pub async fn set(&self, key: &str, value: &T) -> Result<()> {
+        let key = self.full_key(key);
+        let value = serde_json::to_value(value)?;
+        request(StorageOperation::Set { key, value }).await?;
+        Ok(())
+    }
+
+    /// Does nothing if the key isn't set.
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        let key = self.full_key(key);
+        request(StorageOperation::Delete { key }).await?;
+        Ok(())
+    }
+
+    /// Gets all values with keys that start with `prefix`.
+    ///
+    /// Use an empty prefix to get everything in this store. Keys are
+    /// returned without the namespace.
+    pub async fn list(&self, prefix: &str) -> Result<BTreeMap<String, T>> {
+        let namespace_prefix = self.full_key("");
+        let prefix = self.full_key(prefix);
+        let StorageResult::Entries(entries) = request(StorageOperation::List { prefix }).await?
+        else {
+            bail!("unexpected storage result");
+        };
+
+        entries
+            .into_iter()
+            .map(|(key, value)| {
+                let key = key
+                    .strip_prefix(&namespace_prefix)
+                    .unwrap_or(&key)
+                    .to_owned();
+                Ok((key, serde_json::from_value(value)?))
+            })
+            .collect()
+    }
+}
+
+/// Whether the host supports [`covey_proto::Feature::Storage`]. Set after the
+/// handshake.
+static HOST_SUPPORTS_STORAGE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_host_supports_storage(supported: bool) {
+    HOST_SUPPORTS_STORAGE.store(supported, Ordering::Relaxed);
+}
+
+/// Requests that are waiting for a result from the host.
+static PENDING: LazyLock<Mutex<PendingRequests>> = LazyLock::new(Mutex::default);
+
+#[derive(Default)]
+struct PendingRequests {
+    next_id: u64,
+    senders: BTreeMap<StorageId, oneshot::Sender<StorageResult>>,
+}
+
+async fn request(operation: StorageOperation) -> Result<StorageResult> {
+    if !HOST_SUPPORTS_STORAGE.load(Ordering::Relaxed) {
+        bail!("this version of covey does not support storage");
+    }
+
+    let (tx, rx) = oneshot::channel();
+    let storage_id = {
+        let mut pending = PENDING.lock().unwrap();
+        let id = StorageId(pending.next_id);
+        pending.next_id += 1;
+        pending.senders.insert(id, tx);
+        id
+    };
+
+    crate::transport::send(&covey_proto::Response::storage(
+        covey_proto::StorageRequest {
+            storage_id,
+            operation,
+        },
+    ));
+
+    match rx.await.context("covey stopped before replying")? {
+        StorageResult::Error(e) => bail!("{e}"),
+        result => Ok(result),
+    }
+}
+
+pub(crate) fn answer(result: covey_proto::RequestStorageResult) {
+    let sender = PENDING.lock().unwrap().senders.remove(&result.storage_id);
+    match sender {
+        // The caller may have been dropped while waiting.
+        Some(sender) => _ = sender.send(result.result),
+        None => eprintln!(
+            "received result of unknown storage request {:?}",
+            result.storage_id
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Store;
+
+    #[test]
+    #[should_panic = "should not contain '/'"]
+    fn namespace_with_separator() {
+        let _ = Store::<u32>::new("a/b");
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, VecDeque},
     iter,
     ops::Range,
     pin::Pin,
@@ -12,7 +12,7 @@ use std::{
 
 use az::CheckedAs;
 
-use crate::{List, Menu, rank::VisitId};
+use crate::{CommandTitle, List, ListSection, Menu, rank::VisitId};
 
 /// Map list (item) IDs to their command callbacks.
 ///
@@ -21,13 +21,16 @@ use crate::{List, Menu, rank::VisitId};
 pub(crate) struct CommandMap {
     lists: Arc<Mutex<VecDeque<ListCallbacks>>>,
     target_ids: Arc<AutoIncrementer>,
+    /// Decides which parts of the lists can be sent to covey.
+    host: Arc<covey_proto::Hello>,
 }
 
 impl CommandMap {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(host: Arc<covey_proto::Hello>) -> Self {
         Self {
             lists: Arc::new(Mutex::new(VecDeque::new())),
             target_ids: Arc::new(AutoIncrementer(AtomicU64::new(0))),
+            host,
         }
     }
 
@@ -38,42 +41,28 @@ impl CommandMap {
             items,
             section_titles,
             callbacks: list_callbacks,
+            selection,
+            stream: _,
+            updates: _,
         } = list;
 
         let new_ids = self.target_ids.fetch_many(items.len() as u64 + 1);
         let list_target_id = covey_proto::ActivationTarget(new_ids.start);
 
-        let mut proto_items = vec![];
-        let mut item_callbacks = vec![];
-
-        for (id, item) in iter::zip(new_ids.start + 1..new_ids.end, items) {
-            let crate::ListItem {
-                title,
-                description,
-                icon,
-                visit_id,
-                callbacks,
-            } = item;
-
-            let commands = callbacks.ids().cloned().collect();
-            item_callbacks.push((visit_id, callbacks));
-            proto_items.push(covey_proto::ListItem {
-                id: covey_proto::ActivationTarget(id),
-                title,
-                description,
-                icon: icon.map(crate::into_proto::icon),
-                commands,
-            })
-        }
+        let (proto_items, item_callbacks) =
+            split_items(new_ids.start + 1..new_ids.end, items, &self.host);
 
-        let list_command_ids = list_callbacks.ids().cloned().collect();
+        let list_commands = list_callbacks.proto_commands(&self.host);
 
         let num_lists = {
             let mut lists = self.lists.lock().unwrap();
             lists.push_back(ListCallbacks {
                 list_target_id,
                 list_callbacks,
-                item_callbacks,
+                item_chunks: vec![ItemCallbacks {
+                    first_target_id: list_target_id.0 + 1,
+                    callbacks: item_callbacks,
+                }],
             });
             lists.len()
         };
@@ -92,12 +81,52 @@ impl CommandMap {
 
         covey_proto::List {
             id: list_target_id,
-            commands: list_command_ids,
+            commands: list_commands,
             items: proto_items,
             section_titles,
+            selection,
         }
     }
 
+    /// Stores more items of a list previously stored with
+    /// [`Self::store_query_result`], returning the extension that should be
+    /// sent to covey.
+    ///
+    /// Returns [`None`] if the list has already been removed, in which case
+    /// covey is not showing the list anymore.
+    pub(crate) fn extend_query_result(
+        &self,
+        list_target_id: covey_proto::ActivationTarget,
+        section: ListSection,
+    ) -> Option<covey_proto::ListExtension> {
+        let ListSection { title, items } = section;
+
+        let new_ids = self.target_ids.fetch_many(items.len() as u64);
+        let first_target_id = new_ids.start;
+        let (proto_items, item_callbacks) = split_items(new_ids, items, &self.host);
+
+        self.lists
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|list| list.list_target_id == list_target_id)?
+            .item_chunks
+            .push(ItemCallbacks {
+                first_target_id,
+                callbacks: item_callbacks,
+            });
+
+        let mut section_titles = BTreeMap::new();
+        if !title.is_empty() {
+            section_titles.insert(0, title);
+        }
+
+        Some(covey_proto::ListExtension {
+            items: proto_items,
+            section_titles,
+        })
+    }
+
     /// Finds the associated callbacks of an ID.
     ///
     /// This should never return [`None`] if the ID comes from an RPC call.
@@ -118,16 +147,130 @@ impl CommandMap {
             callbacks.get_callback(command_id)?.clone(),
         ))
     }
+
+    /// Finds the batch callback of the list that the targets are in.
+    ///
+    /// Returns [`None`] if the list has no batch callback for this command,
+    /// in which case each target should be activated separately.
+    pub(crate) fn find_batch_callback(
+        &self,
+        target_ids: &[covey_proto::ActivationTarget],
+        command_id: &covey_proto::CommandId,
+    ) -> Option<BatchActivation> {
+        let first_target_id = *target_ids.first()?;
+        let lists = self.lists.lock().unwrap();
+        let list = lists
+            .iter()
+            .find(|list| list.item(first_target_id).is_some())?;
+        let callback = list.list_callbacks.batch_commands.get(command_id)?.clone();
+
+        let mut activation = BatchActivation {
+            callback,
+            keys: vec![],
+            visit_ids: vec![],
+            unkeyed: vec![],
+        };
+        for &target_id in target_ids {
+            match list.item(target_id) {
+                Some(StoredItem {
+                    visit_id,
+                    key: Some(key),
+                    ..
+                }) => {
+                    activation.keys.push(key.clone());
+                    activation.visit_ids.push(visit_id.clone());
+                }
+                _ => activation.unkeyed.push(target_id),
+            }
+        }
+        Some(activation)
+    }
+}
+
+/// A batch callback and the targets to activate with it.
+pub(crate) struct BatchActivation {
+    pub(crate) callback: BatchActivationFunction,
+    /// Keys of the targets to pass to the callback.
+    pub(crate) keys: Vec<String>,
+    pub(crate) visit_ids: Vec<VisitId>,
+    /// Targets without a key, which should be activated separately.
+    pub(crate) unkeyed: Vec<covey_proto::ActivationTarget>,
+}
+
+/// Converts list items into their protocol representation, splitting off
+/// their callbacks.
+///
+/// `ids` should have the same length as `items`.
+fn split_items(
+    ids: Range<u64>,
+    items: Vec<crate::ListItem>,
+    host: &covey_proto::Hello,
+) -> (Vec<covey_proto::ListItem>, Vec<StoredItem>) {
+    debug_assert_eq!(ids.end - ids.start, items.len() as u64);
+
+    let mut proto_items = vec![];
+    let mut item_callbacks = vec![];
+
+    for (id, item) in iter::zip(ids, items) {
+        let crate::ListItem {
+            title,
+            description,
+            icon,
+            preview,
+            accessory,
+            tags,
+            highlights,
+            key,
+            visit_id,
+            callbacks,
+        } = item;
+
+        let commands = callbacks.proto_commands(host);
+        item_callbacks.push(StoredItem {
+            visit_id,
+            key: key.clone(),
+            callbacks,
+        });
+        proto_items.push(covey_proto::ListItem {
+            id: covey_proto::ActivationTarget(id),
+            title,
+            description,
+            icon: icon.and_then(|icon| crate::into_proto::icon(icon, host)),
+            commands,
+            key,
+            preview: preview.map(crate::into_proto::preview),
+            accessory,
+            tags: tags.into_iter().map(crate::into_proto::tag).collect(),
+            highlights: crate::into_proto::highlights(highlights),
+        })
+    }
+
+    (proto_items, item_callbacks)
 }
 
 /// INVARIANTS:
-/// - IDs of the list items are increasing and contiguous.
 /// - `list_target_id` is the target id of the list itself.
-/// - The IDs of the list items start at `list_target_id + 1`.
+/// - The IDs of the list items in the first chunk start at `list_target_id +
+///   1`.
 struct ListCallbacks {
     list_target_id: covey_proto::ActivationTarget,
     list_callbacks: TargetCallbacks,
-    item_callbacks: Vec<(VisitId, TargetCallbacks)>,
+    /// Items are split into chunks if more items are added to a streamed list.
+    item_chunks: Vec<ItemCallbacks>,
+}
+
+/// INVARIANTS:
+/// - IDs of the list items are increasing and contiguous.
+/// - The IDs of the list items start at `first_target_id`.
+struct ItemCallbacks {
+    first_target_id: u64,
+    callbacks: Vec<StoredItem>,
+}
+
+struct StoredItem {
+    visit_id: VisitId,
+    key: Option<String>,
+    callbacks: TargetCallbacks,
 }
 
 impl ListCallbacks {
@@ -138,15 +281,20 @@ impl ListCallbacks {
         if target.0 == self.list_target_id.0 {
             Some((None, &self.list_callbacks))
         } else {
-            let offset = target.0.checked_sub(self.list_target_id.0 + 1)?;
-            let (id, callbacks) = self.item_callbacks.get(
+            let item = self.item(target)?;
+            Some((Some(&item.visit_id), &item.callbacks))
+        }
+    }
+
+    fn item(&self, target: covey_proto::ActivationTarget) -> Option<&StoredItem> {
+        self.item_chunks.iter().find_map(|chunk| {
+            let offset = target.0.checked_sub(chunk.first_target_id)?;
+            chunk.callbacks.get(
                 offset
                     .checked_as::<usize>()
                     .expect("there should not be way too many callbacks stored (over u32::MAX)"),
-            )?;
-
-            Some((Some(id), callbacks))
-        }
+            )
+        })
     }
 }
 
@@ -155,27 +303,77 @@ impl ListCallbacks {
 // `Menu` to force the user to complete everything they want before returning
 // from the callback. Might want to add something that happens after the
 // callback returns.
-type DynFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+pub(crate) type DynFuture<T> = Pin<Box<dyn Future<Output = T>>>;
 type ActivationFunction = Arc<dyn Fn(Menu) -> DynFuture<()> + Send + Sync>;
+type BatchActivationFunction = Arc<dyn Fn(Menu, Vec<String>) -> DynFuture<()> + Send + Sync>;
 
 #[derive(Clone)]
 pub(crate) struct TargetCallbacks {
     commands: HashMap<covey_proto::CommandId, ActivationFunction>,
+    /// Commands that aren't in the manifest, in the order they were added.
+    runtime_commands: Vec<covey_proto::Command>,
+    /// Manifest commands with a different title for this target.
+    titles: HashMap<covey_proto::CommandId, CommandTitle>,
+    /// Callbacks that activate several items of a list at once.
+    batch_commands: HashMap<covey_proto::CommandId, BatchActivationFunction>,
 }
 
 impl TargetCallbacks {
     pub(crate) fn new() -> Self {
         Self {
             commands: HashMap::default(),
+            runtime_commands: vec![],
+            titles: HashMap::default(),
+            batch_commands: HashMap::default(),
         }
     }
 
+    pub(crate) fn add_batch_callback(
+        &mut self,
+        command_id: covey_proto::CommandId,
+        callback: impl AsyncFn(&Menu, Vec<String>) -> crate::Result<()> + Send + Sync + 'static,
+    ) {
+        let callback = Arc::new(callback);
+        self.batch_commands.insert(
+            command_id,
+            Arc::new(move |menu, keys| {
+                let callback = Arc::clone(&callback);
+                Box::pin(async move {
+                    if let Err(e) = callback(&menu, keys).await {
+                        menu.display_error(format!("{e:#}"));
+                    }
+                })
+            }),
+        );
+    }
+
+    pub(crate) fn add_titled_callback(
+        &mut self,
+        command_id: covey_proto::CommandId,
+        title: CommandTitle,
+        callback: impl AsyncFn(&Menu) -> crate::Result<()> + Send + Sync + 'static,
+    ) {
+        self.add_callback(command_id.clone(), callback);
+        self.titles.insert(command_id, title);
+    }
+
+    pub(crate) fn add_runtime_callback(
+        &mut self,
+        command: covey_proto::Command,
+        callback: impl AsyncFn(&Menu) -> crate::Result<()> + Send + Sync + 'static,
+    ) {
+        self.add_callback(command.id.clone(), callback);
+        self.runtime_commands.retain(|cmd| cmd.id != command.id);
+        self.runtime_commands.push(command);
+    }
+
     pub(crate) fn add_callback(
         &mut self,
         command_id: covey_proto::CommandId,
         callback: impl AsyncFn(&Menu) -> crate::Result<()> + Send + Sync + 'static,
     ) {
         let callback = Arc::new(callback);
+        self.titles.remove(&command_id);
         self.commands.insert(
             command_id,
             Arc::new(move |menu| {
@@ -196,8 +394,36 @@ impl TargetCallbacks {
         self.commands.get(command_id)
     }
 
-    pub(crate) fn ids(&self) -> impl Iterator<Item = &covey_proto::CommandId> {
-        self.commands.keys()
+    /// The commands with a callback, as sent to covey.
+    ///
+    /// Hosts that don't support runtime commands don't get them, and show
+    /// manifest commands with their usual titles.
+    pub(crate) fn proto_commands(
+        &self,
+        host: &covey_proto::Hello,
+    ) -> Vec<covey_proto::ListItemCommand> {
+        let manifest_commands = self
+            .commands
+            .keys()
+            .filter(|id| !self.runtime_commands.iter().any(|cmd| cmd.id == **id))
+            .map(|id| match self.titles.get(id) {
+                Some(title) if host.supports(covey_proto::Feature::CommandTitles) => {
+                    covey_proto::ListItemCommand::Overridden(covey_proto::CommandOverride {
+                        manifest: id.clone(),
+                        title: title.title.clone(),
+                        description: title.description.clone(),
+                    })
+                }
+                _ => covey_proto::ListItemCommand::Manifest(id.clone()),
+            });
+        let runtime_commands = self
+            .runtime_commands
+            .iter()
+            .filter(|_| host.supports(covey_proto::Feature::RuntimeCommands))
+            .cloned()
+            .map(covey_proto::ListItemCommand::Runtime);
+
+        manifest_commands.chain(runtime_commands).collect()
     }
 }
 
@@ -213,3 +439,99 @@ impl AutoIncrementer {
         lower_bound..upper_bound
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, sync::Arc};
+
+    use super::CommandMap;
+    use crate::{Icon, List, ListItem, ListSection, RuntimeCommand};
+
+    #[tokio::test]
+    async fn find_callback_in_extended_list() {
+        let map = CommandMap::new(Arc::new(covey_proto::Hello::current()));
+        let activate = covey_proto::CommandId::new("activate");
+        let item = |title: &str| ListItem::new(title).add_command("activate", async |_| Ok(()));
+
+        let list = map.store_query_result(List::new(vec![item("a"), item("b")]));
+        // Another list taking IDs in between the list and its extension.
+        let other = map.store_query_result(List::new(vec![item("c")]));
+        let extension = map
+            .extend_query_result(list.id, ListSection::new("more", vec![item("d")]))
+            .unwrap();
+
+        assert_eq!(extension.section_titles.get(&0).unwrap(), "more");
+        for target in [
+            &list.items[0],
+            &list.items[1],
+            &other.items[0],
+            &extension.items[0],
+        ] {
+            assert!(map.find_callback(target.id, &activate).is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn find_batch_callback_with_keys() {
+        let map = CommandMap::new(Arc::new(covey_proto::Hello::current()));
+        let activate = covey_proto::CommandId::new("activate");
+        let list = List::new(vec![
+            ListItem::new("a").with_key("a"),
+            ListItem::new("b"),
+            ListItem::new("c").with_key("c"),
+        ])
+        .add_batch_command("activate", async |_, _| Ok(()));
+        let list = map.store_query_result(list);
+
+        let targets: Vec<_> = list.items.iter().map(|item| item.id).collect();
+        let batch = map.find_batch_callback(&targets, &activate).unwrap();
+        assert_eq!(batch.keys, ["a", "c"]);
+        assert_eq!(batch.unkeyed, [list.items[1].id]);
+
+        let other = covey_proto::CommandId::new("other");
+        assert!(map.find_batch_callback(&targets, &other).is_none());
+    }
+
+    #[tokio::test]
+    async fn unsupported_commands_are_not_sent() {
+        let host = covey_proto::Hello {
+            protocol_version: covey_proto::PROTOCOL_VERSION,
+            features: BTreeSet::new(),
+        };
+        let map = CommandMap::new(Arc::new(host));
+        let item = ListItem::new("a")
+            .add_command_with_title("activate", "Open", async |_| Ok(()))
+            .with_command(RuntimeCommand::new("runtime", "Runtime"), async |_| Ok(()));
+
+        let list = map.store_query_result(List::new(vec![item]));
+        assert_eq!(
+            list.items[0].commands,
+            [covey_proto::ListItemCommand::Manifest(
+                covey_proto::CommandId::new("activate")
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn rich_icons_fall_back_to_names() {
+        let host = covey_proto::Hello {
+            protocol_version: covey_proto::PROTOCOL_VERSION,
+            features: BTreeSet::new(),
+        };
+        let map = CommandMap::new(Arc::new(host));
+        let list = map.store_query_result(List::new(vec![
+            ListItem::new("a").with_icon_data(vec![0]),
+            ListItem::new("b").with_icon(Some(Icon::Fallback(vec![
+                Icon::File("/tmp/icon.png".into()),
+                Icon::Name("folder".to_owned()),
+                Icon::Text("B".to_owned()),
+            ]))),
+        ]));
+
+        assert_eq!(list.items[0].icon, None);
+        assert_eq!(
+            list.items[1].icon,
+            Some(covey_proto::ListItemIcon::Name("folder".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Connection to Covey.
+//!
+//! Covey passes the name of a local socket in `COVEY_SOCKET`, which leaves
+//! stdin/out free for the plugin and its dependencies to use. If connecting
+//! fails, the protocol is sent over stdin/out.
+
+use std::{
+    io::{self, BufRead as _, BufReader, Write as _},
+    sync::{Mutex, OnceLock},
+};
+
+use interprocess::local_socket::{
+    GenericNamespaced, SendHalf, Stream, ToNsName as _, traits::Stream as _,
+};
+use tokio::{
+    io::{AsyncBufReadExt as _, Lines, Stdin},
+    sync::mpsc,
+};
+
+const SOCKET_ENV_VAR: &str = "COVEY_SOCKET";
+
+/// Set if connected to Covey's socket.
+static SOCKET: OnceLock<Mutex<SendHalf>> = OnceLock::new();
+
+/// Requests sent by Covey.
+pub(crate) enum Requests {
+    Stdin(Lines<tokio::io::BufReader<Stdin>>),
+    /// Read in another thread, as the socket is blocking.
+    Socket(mpsc::UnboundedReceiver<io::Result<String>>),
+}
+
+impl Requests {
+    /// Connects to the socket passed by Covey, using stdin/out if there is
+    /// none.
+    ///
+    /// Should only be called once, before anything is written to stdout.
+    pub(crate) fn connect() -> Self {
+        let Ok(name) = std::env::var(SOCKET_ENV_VAR) else {
+            return Self::stdin();
+        };
+
+        let stream = name
+            .as_str()
+            .to_ns_name::<GenericNamespaced>()
+            .and_then(Stream::connect);
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("failed to connect to {name}, using stdin/out: {e:#}");
+                return Self::stdin();
+            }
+        };
+
+        // Covey waits for this before using the socket.
+        println!("{}", covey_proto::SOCKET_CONNECTED);
+
+        let (recv, send) = stream.split();
+        assert!(
+            SOCKET.set(Mutex::new(send)).is_ok(),
+            "should only connect to covey once"
+        );
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(recv).lines() {
+                if tx.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+        Self::Socket(rx)
+    }
+
+    fn stdin() -> Self {
+        Self::Stdin(tokio::io::BufReader::new(tokio::io::stdin()).lines())
+    }
+
+    /// Returns [`None`] once Covey closes the connection.
+    pub(crate) async fn next_line(&mut self) -> io::Result<Option<String>> {
+        match self {
+            Self::Stdin(lines) => lines.next_line().await,
+            Self::Socket(lines) => lines.recv().await.transpose(),
+        }
+    }
+}
+
+/// Sends a response to Covey.
+pub(crate) fn send(response: &covey_proto::Response) {
+    let Some(socket) = SOCKET.get() else {
+        println!("{}", response.serialize());
+        return;
+    };
+
+    let mut json = response.serialize();
+    json.push('\n');
+    let mut socket = socket.lock().unwrap();
+    if let Err(e) = socket
+        .write_all(json.as_bytes())
+        .and_then(|()| socket.flush())
+    {
+        eprintln!("failed to send response to covey: {e:#}");
+    }
+}
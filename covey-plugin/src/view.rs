@@ -0,0 +1,46 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{List, store::DynFuture};
+
+type QueryFunction = Arc<dyn Fn(String) -> DynFuture<crate::Result<List>> + Send + Sync>;
+
+/// Query callbacks of views pushed with
+/// [`Menu::push_view`](crate::Menu::push_view).
+#[derive(Clone, Default)]
+pub(crate) struct Views(Arc<Mutex<ViewsInner>>);
+
+#[derive(Default)]
+struct ViewsInner {
+    next_id: u64,
+    queries: BTreeMap<covey_proto::ViewId, QueryFunction>,
+}
+
+impl Views {
+    pub(crate) fn insert(
+        &self,
+        query: impl AsyncFn(String) -> crate::Result<List> + Send + Sync + 'static,
+    ) -> covey_proto::ViewId {
+        let query = Arc::new(query);
+        let query: QueryFunction = Arc::new(move |text| {
+            let query = Arc::clone(&query);
+            Box::pin(async move { query(text).await })
+        });
+
+        let mut inner = self.0.lock().unwrap();
+        let id = covey_proto::ViewId(inner.next_id);
+        inner.next_id += 1;
+        inner.queries.insert(id, query);
+        id
+    }
+
+    pub(crate) fn get(&self, id: covey_proto::ViewId) -> Option<QueryFunction> {
+        self.0.lock().unwrap().queries.get(&id).cloned()
+    }
+
+    pub(crate) fn remove(&self, id: covey_proto::ViewId) {
+        self.0.lock().unwrap().queries.remove(&id);
+    }
+}
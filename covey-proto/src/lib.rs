@@ -1,14 +1,58 @@
-//! Messages sent as newline delimited JSON over stdin/out.
+//! Messages sent as newline delimited JSON between covey and plugins.
+//!
+//! Plugins should connect to the local socket named by the `COVEY_SOCKET`
+//! environment variable and use it for all messages. Once connected, the
+//! plugin must write [`SOCKET_CONNECTED`] as the first line of its stdout,
+//! which tells the host to use the socket. Plugins that don't connect use
+//! their stdin/out instead.
+//!
+//! The plugin's first message must be a [`ResponseBody::Hello`], sent
+//! without waiting for the host, as the host buffers its requests until it
+//! knows which connection the plugin uses. The host's first message is a
+//! [`RequestBody::Hello`], followed by a [`RequestBody::Initialize`] with the
+//! plugin's settings.
 
-use std::{collections::BTreeMap, ops::Range};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+};
 
-pub use covey_schema::id::CommandId;
+pub use covey_schema::{
+    hotkey::Hotkey,
+    id::CommandId,
+    manifest::Command,
+    style::{Color, TagColor},
+};
 use serde::{Deserialize, Serialize};
 
+/// Version of the protocol defined by this crate.
+///
+/// This should be increased whenever a change is made that can't be
+/// understood by an older host or plugin. Smaller additions should add a
+/// [`Feature`] instead.
+///
+/// Hosts and plugins with different versions can't talk to each other, see
+/// [`Hello::is_compatible_with`]. Version 2 sends the settings in
+/// [`RequestBody::Initialize`] instead of as an argument, so plugins built for
+/// version 1 must be rebuilt.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Written to stdout by plugins that connected to the socket in
+/// `COVEY_SOCKET`, before anything else.
+pub const SOCKET_CONNECTED: &str = "covey-socket-connected";
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(transparent)]
 pub struct RequestId(pub u64);
 
+impl RequestId {
+    /// Reserved for messages about the connection itself rather than a
+    /// particular query or activation, like the handshake.
+    pub const CONTROL: Self = Self(0);
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Request {
@@ -17,10 +61,89 @@ pub struct Request {
 }
 
 impl Request {
-    pub fn query(id: RequestId, query: String) -> Self {
+    pub fn hello(hello: Hello) -> Self {
+        Self {
+            id: RequestId::CONTROL,
+            request: RequestBody::Hello(hello),
+        }
+    }
+
+    /// Sent right after [`Request::hello`].
+    pub fn initialize(initialize: Initialize) -> Self {
+        Self {
+            id: RequestId::CONTROL,
+            request: RequestBody::Initialize(initialize),
+        }
+    }
+
+    /// Changes some of the settings sent in [`Request::initialize`].
+    pub fn configure(
+        changed: serde_json::Map<String, serde_json::Value>,
+        removed: Vec<String>,
+    ) -> Self {
+        Self {
+            id: RequestId::CONTROL,
+            request: RequestBody::Configure(RequestConfigure { changed, removed }),
+        }
+    }
+
+    /// Tells the plugin about a change in the host's state.
+    pub fn lifecycle(event: LifecycleEvent) -> Self {
+        Self {
+            id: RequestId::CONTROL,
+            request: RequestBody::Lifecycle(event),
+        }
+    }
+
+    /// Cancels a previous request that is no longer needed.
+    pub fn cancel(request_id: RequestId) -> Self {
+        Self {
+            id: RequestId::CONTROL,
+            request: RequestBody::Cancel(RequestCancel { request_id }),
+        }
+    }
+
+    /// Answers a [`PluginAction::Prompt`].
+    ///
+    /// `answer` is [`None`] if the user dismissed the prompt.
+    pub fn prompt_answer(prompt_id: PromptId, answer: Option<PromptAnswer>) -> Self {
+        Self {
+            id: RequestId::CONTROL,
+            request: RequestBody::PromptAnswer(RequestPromptAnswer { prompt_id, answer }),
+        }
+    }
+
+    /// Replies to a [`ResponseBody::Storage`].
+    pub fn storage_result(storage_id: StorageId, result: StorageResult) -> Self {
+        Self {
+            id: RequestId::CONTROL,
+            request: RequestBody::StorageResult(RequestStorageResult { storage_id, result }),
+        }
+    }
+
+    /// Queries the plugin, or one of its views if `view` is [`Some`].
+    pub fn query(
+        id: RequestId,
+        query: String,
+        view: Option<ViewId>,
+        context: Option<QueryContext>,
+    ) -> Self {
         Self {
             id,
-            request: RequestBody::Query(RequestQuery { text: query }),
+            request: RequestBody::Query(RequestQuery {
+                text: query,
+                view,
+                context,
+            }),
+        }
+    }
+
+    /// Tells the plugin that a view has been closed and won't be queried
+    /// again.
+    pub fn close_view(view_id: ViewId) -> Self {
+        Self {
+            id: RequestId::CONTROL,
+            request: RequestBody::CloseView(RequestCloseView { view_id }),
         }
     }
 
@@ -34,6 +157,20 @@ impl Request {
         }
     }
 
+    pub fn activate_many(
+        id: RequestId,
+        target_ids: Vec<ActivationTarget>,
+        command_id: CommandId,
+    ) -> Self {
+        Self {
+            id,
+            request: RequestBody::ActivateMany(RequestActivateMany {
+                target_ids,
+                command_id,
+            }),
+        }
+    }
+
     /// Does not include a newline at the end.
     pub fn serialize(&self) -> String {
         serde_json::to_string(self).expect("request should always be serializable")
@@ -43,14 +180,183 @@ impl Request {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum RequestBody {
+    /// Sent once when the plugin starts.
+    Hello(Hello),
+    /// Sent once after [`RequestBody::Hello`], before any other request.
+    ///
+    /// Added in protocol version 2. Older hosts passed the settings as the
+    /// first command line argument instead.
+    Initialize(Initialize),
     Query(RequestQuery),
     Activate(RequestActivate),
+    /// Requires [`Feature::Cancellation`].
+    Cancel(RequestCancel),
+    /// Requires [`Feature::Prompts`].
+    PromptAnswer(RequestPromptAnswer),
+    /// Requires [`Feature::Views`].
+    CloseView(RequestCloseView),
+    /// Requires [`Feature::BatchActivation`].
+    ActivateMany(RequestActivateMany),
+    /// Requires [`Feature::Reconfigure`].
+    Configure(RequestConfigure),
+    /// Requires [`Feature::Lifecycle`].
+    Lifecycle(LifecycleEvent),
+    /// Requires [`Feature::Storage`].
+    StorageResult(RequestStorageResult),
+}
+
+/// The protocol version and features supported by one side of the connection.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Hello {
+    pub protocol_version: u32,
+    pub features: BTreeSet<Feature>,
+}
+
+impl Hello {
+    /// The protocol version and all features defined by this crate.
+    pub fn current() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            features: BTreeSet::from([
+                Feature::StreamingLists,
+                Feature::Cancellation,
+                Feature::Prompts,
+                Feature::Views,
+                Feature::ListUpdates,
+                Feature::BatchActivation,
+                Feature::RichClipboard,
+                Feature::Reconfigure,
+                Feature::Lifecycle,
+                Feature::Logs,
+                Feature::Storage,
+                Feature::RuntimeCommands,
+                Feature::CommandTitles,
+                Feature::RichIcons,
+                Feature::Opener,
+                Feature::Notifications,
+                Feature::Progress,
+            ]),
+        }
+    }
+
+    /// Whether both sides use the same protocol version.
+    ///
+    /// Every version change is a breaking change, so there is no range of
+    /// compatible versions.
+    pub fn is_compatible_with(&self, other: &Hello) -> bool {
+        self.protocol_version == other.protocol_version
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// Everything a plugin needs to start.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Initialize {
+    /// The user's settings for this plugin, as described by its manifest.
+    pub settings: serde_json::Map<String, serde_json::Value>,
+    pub covey_version: String,
+    /// Directory where the plugin's binary and manifest are, and where it can
+    /// store extra data.
+    pub data_dir: PathBuf,
+    /// Language of the user, like `en-AU`, if it is known.
+    pub locale: Option<String>,
+    pub theme: Theme,
+}
+
+/// Colors of the host's window, for plugins that generate images.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Theme {
+    pub background: Color,
+    pub text: Color,
+    pub weak_text: Color,
+    /// Used to highlight parts of the text, like characters matching the
+    /// query.
+    pub accent: Color,
+}
+
+/// An optional part of the protocol.
+///
+/// A feature should only be used if both the host and plugin support it.
+/// Every enum variant that a peer may not understand requires a feature, as
+/// an unknown variant makes the whole message fail to parse.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Feature {
+    /// [`ResponseBody::SetPartialList`] and the related responses.
+    StreamingLists,
+    /// [`RequestBody::Cancel`].
+    Cancellation,
+    /// [`PluginAction::Prompt`] and [`RequestBody::PromptAnswer`].
+    Prompts,
+    /// [`PluginAction::PushView`] and the related requests.
+    Views,
+    /// [`ResponseBody::UpdateList`].
+    ListUpdates,
+    /// [`RequestBody::ActivateMany`].
+    BatchActivation,
+    /// [`PluginAction::CopyContent`].
+    RichClipboard,
+    /// [`RequestBody::Configure`].
+    Reconfigure,
+    /// [`RequestBody::Lifecycle`].
+    Lifecycle,
+    /// [`ResponseBody::Log`].
+    Logs,
+    /// [`ResponseBody::Storage`] and [`RequestBody::StorageResult`].
+    Storage,
+    /// [`ListItemCommand::Runtime`].
+    RuntimeCommands,
+    /// [`ListItemCommand::Overridden`].
+    CommandTitles,
+    /// [`ListItemIcon::File`], [`ListItemIcon::Data`] and
+    /// [`ListItemIcon::Fallback`].
+    RichIcons,
+    /// [`PluginAction::OpenUrl`] and [`PluginAction::OpenPath`].
+    Opener,
+    /// [`PluginAction::Notify`].
+    Notifications,
+    /// [`PluginAction::Progress`] and [`PluginAction::EndProgress`].
+    Progress,
+    /// A feature that is not known by this version of the protocol.
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct RequestQuery {
     pub text: String,
+    /// The view being queried, or [`None`] for the plugin's main list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<ViewId>,
+    /// Not sent by older hosts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<QueryContext>,
+}
+
+/// Where a [`RequestQuery`] came from.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct QueryContext {
+    /// The entire input, including the prefix.
+    pub raw: String,
+    /// The prefix that was removed from [`Self::raw`] to get
+    /// [`RequestQuery::text`].
+    ///
+    /// Empty when a view is queried.
+    pub prefix: String,
+    /// Position of the cursor in [`Self::raw`], in characters.
+    #[serde(default)]
+    pub cursor: Option<usize>,
+    /// Whether this is the first query since the window was opened.
+    #[serde(default)]
+    pub is_first: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -60,6 +366,93 @@ pub struct RequestActivate {
     pub command_id: CommandId,
 }
 
+/// Activates the same command on several items of one list.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RequestActivateMany {
+    pub target_ids: Vec<ActivationTarget>,
+    pub command_id: CommandId,
+}
+
+/// Settings that changed since the plugin was initialized or last
+/// configured.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RequestConfigure {
+    /// Settings that were added or given a new value.
+    pub changed: serde_json::Map<String, serde_json::Value>,
+    /// Settings that were removed, so the default should be used.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+}
+
+/// A change in the host's state that plugins may want to react to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LifecycleEvent {
+    /// The launcher window has been opened.
+    WindowShown,
+    /// The launcher window has been closed.
+    WindowHidden,
+    /// The plugin should save its state and exit.
+    ///
+    /// The host kills the process if it hasn't exited after a short grace
+    /// period.
+    Shutdown,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RequestStorageResult {
+    pub storage_id: StorageId,
+    pub result: StorageResult,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageResult {
+    /// Result of [`StorageOperation::Get`], [`None`] if the key isn't set.
+    Value(Option<serde_json::Value>),
+    /// Result of [`StorageOperation::List`].
+    Entries(BTreeMap<String, serde_json::Value>),
+    /// Result of [`StorageOperation::Set`] and [`StorageOperation::Delete`].
+    Done,
+    /// The operation failed and nothing was changed.
+    Error(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RequestCancel {
+    /// The request to cancel.
+    pub request_id: RequestId,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RequestCloseView {
+    pub view_id: ViewId,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RequestPromptAnswer {
+    pub prompt_id: PromptId,
+    /// [`None`] if the prompt was dismissed.
+    pub answer: Option<PromptAnswer>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptAnswer {
+    /// Answer to [`PromptKind::Text`].
+    Text(String),
+    /// Index of the option chosen in [`PromptKind::Choice`].
+    Choice(usize),
+    /// Answer to [`PromptKind::Confirm`].
+    Confirm(bool),
+}
+
 /// A response sent by the plugin against a [`Request`].
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "kebab-case")]
@@ -73,6 +466,27 @@ impl Response {
         serde_json::to_string(self).expect("response should be serializable")
     }
 
+    pub fn hello(hello: Hello) -> Self {
+        Self {
+            request_id: RequestId::CONTROL,
+            response: ResponseBody::Hello(hello),
+        }
+    }
+
+    pub fn log(record: LogRecord) -> Self {
+        Self {
+            request_id: RequestId::CONTROL,
+            response: ResponseBody::Log(record),
+        }
+    }
+
+    pub fn storage(request: StorageRequest) -> Self {
+        Self {
+            request_id: RequestId::CONTROL,
+            response: ResponseBody::Storage(request),
+        }
+    }
+
     pub fn set_list(request_id: RequestId, list: List) -> Self {
         Self {
             request_id,
@@ -80,6 +494,34 @@ impl Response {
         }
     }
 
+    pub fn set_partial_list(request_id: RequestId, list: List) -> Self {
+        Self {
+            request_id,
+            response: ResponseBody::SetPartialList(list),
+        }
+    }
+
+    pub fn extend_list(request_id: RequestId, extension: ListExtension) -> Self {
+        Self {
+            request_id,
+            response: ResponseBody::ExtendList(extension),
+        }
+    }
+
+    pub fn update_list(request_id: RequestId, list: List) -> Self {
+        Self {
+            request_id,
+            response: ResponseBody::UpdateList(list),
+        }
+    }
+
+    pub fn finish_list(request_id: RequestId) -> Self {
+        Self {
+            request_id,
+            response: ResponseBody::FinishList,
+        }
+    }
+
     pub fn perform_action(request_id: RequestId, action: PluginAction) -> Self {
         Self {
             request_id,
@@ -95,10 +537,93 @@ impl Response {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub enum ResponseBody {
+    /// Sent once when the plugin starts, before receiving the
+    /// [`RequestBody::Hello`].
+    Hello(Hello),
     /// Response to [`RequestBody::Query`].
     SetList(List),
+    /// Response to [`RequestBody::Query`] where more items will be sent later.
+    ///
+    /// Items are added with [`ResponseBody::ExtendList`] until a
+    /// [`ResponseBody::FinishList`] is sent.
+    SetPartialList(List),
+    /// Appends items to the partial list with the same request ID.
+    ExtendList(ListExtension),
+    /// Marks the partial list with the same request ID as complete.
+    FinishList,
+    /// Replaces the list sent for the query with the same request ID, without
+    /// the user changing the query. Requires [`Feature::ListUpdates`].
+    ///
+    /// Can be sent any number of times after the list is complete.
+    UpdateList(List),
     /// Response to [`RequestBody::Activate`]. Can be sent multiple times.
     PerformAction(PluginAction),
+    /// Reads or writes the plugin's storage. Requires [`Feature::Storage`].
+    ///
+    /// Can be sent at any time, usually with [`RequestId::CONTROL`]. The host
+    /// replies with a [`RequestBody::StorageResult`].
+    Storage(StorageRequest),
+    /// A log message from the plugin. Requires [`Feature::Logs`].
+    ///
+    /// Can be sent at any time, usually with [`RequestId::CONTROL`].
+    Log(LogRecord),
+}
+
+/// An operation on the plugin's key/value storage.
+///
+/// Each plugin has its own storage, so keys don't need to include the
+/// plugin's ID.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct StorageRequest {
+    pub storage_id: StorageId,
+    pub operation: StorageOperation,
+}
+
+/// Chosen by the plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct StorageId(pub u64);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageOperation {
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        value: serde_json::Value,
+    },
+    Delete {
+        key: String,
+    },
+    /// Gets all entries with keys that start with `prefix`.
+    List {
+        prefix: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogRecord {
+    pub level: LogLevel,
+    /// Module path where the message was logged, or a custom target.
+    pub target: String,
+    pub message: String,
+    /// Extra key/value pairs recorded with the message.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -108,6 +633,123 @@ pub enum PluginAction {
     Copy(String),
     SetInput(Input),
     DisplayError(String),
+    /// Opens a URL with the user's configured opener. Requires
+    /// [`Feature::Opener`].
+    OpenUrl(String),
+    /// Opens a file or directory with the user's configured opener. Requires
+    /// [`Feature::Opener`].
+    OpenPath(PathBuf),
+    /// Shows a message to the user. Requires [`Feature::Notifications`].
+    Notify(Notification),
+    /// Asks the user for input. Requires [`Feature::Prompts`].
+    ///
+    /// The host replies with a [`RequestBody::PromptAnswer`].
+    Prompt(Prompt),
+    /// Shows the progress of the request this is a response to. Requires
+    /// [`Feature::Progress`].
+    ///
+    /// Replaces any progress previously sent for the same request. The host
+    /// may cancel the request with [`RequestBody::Cancel`].
+    Progress(Progress),
+    /// Hides the progress of the request this is a response to. Requires
+    /// [`Feature::Progress`].
+    EndProgress,
+    /// Shows a new list on top of the current one. Requires [`Feature::Views`].
+    ///
+    /// Queries are sent to the view with [`RequestQuery::view`] until the user
+    /// goes back, which sends a [`RequestBody::CloseView`].
+    PushView(View),
+    /// Copies something other than plain text.
+    /// Requires [`Feature::RichClipboard`].
+    CopyContent(ClipboardContent),
+}
+
+/// Content to put in the clipboard.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardContent {
+    Text(String),
+    /// HTML, with plain text for applications that can't paste HTML.
+    Html {
+        html: String,
+        alt_text: Option<String>,
+    },
+    /// Contents of an image file, such as a PNG.
+    ///
+    /// Encoded as base64 in JSON.
+    Image(#[serde(with = "base64_bytes")] Arc<[u8]>),
+    /// Absolute paths of files, as copied by a file manager.
+    Files(Vec<PathBuf>),
+}
+
+/// Identifies a [`View`] of a plugin. Chosen by the plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct ViewId(pub u64);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct View {
+    pub id: ViewId,
+    /// Shown in the breadcrumbs of the host.
+    pub title: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Progress {
+    /// Between 0 and 1, or [`None`] if the progress is unknown.
+    pub fraction: Option<f32>,
+    pub message: String,
+}
+
+/// Identifies a [`Prompt`] so that its answer can be matched up with it.
+///
+/// Chosen by the plugin.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct PromptId(pub u64);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Prompt {
+    pub id: PromptId,
+    pub title: String,
+    pub kind: PromptKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptKind {
+    /// A single line of text.
+    Text {
+        placeholder: String,
+        initial: String,
+    },
+    /// One of several options.
+    Choice { options: Vec<String> },
+    /// A yes/no question.
+    Confirm,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Notification {
+    pub severity: Severity,
+    pub title: String,
+    pub body: String,
+    /// How long to show the notification for. [`None`] uses the default
+    /// timeout of the frontend.
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -136,7 +778,72 @@ pub struct List {
     ///
     /// If a list item has an available command with the same command ID, the
     /// list item command will be ran instead of this command.
-    pub commands: Vec<CommandId>,
+    pub commands: Vec<ListItemCommand>,
+    /// Item to select when this list is shown.
+    ///
+    /// If not provided, the frontend keeps the selection on the item with
+    /// the same [`ListItem::key`] as the previously selected item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection: Option<ListSelection>,
+}
+
+/// An item of a [`List`] to select.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListSelection {
+    Index(usize),
+    /// The first item with this [`ListItem::key`].
+    Key(String),
+}
+
+/// A command that can be activated on a [`List`] or [`ListItem`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ListItemCommand {
+    /// A command declared in the plugin's manifest.
+    Manifest(CommandId),
+    /// A command declared in the plugin's manifest, shown with a different
+    /// title and description for this target. Requires
+    /// [`Feature::CommandTitles`].
+    Overridden(CommandOverride),
+    /// A command created by the plugin while running, which is not in the
+    /// manifest. Requires [`Feature::RuntimeCommands`].
+    Runtime(Command),
+}
+
+impl ListItemCommand {
+    pub fn id(&self) -> &CommandId {
+        match self {
+            ListItemCommand::Manifest(id) => id,
+            ListItemCommand::Overridden(command) => &command.manifest,
+            ListItemCommand::Runtime(command) => &command.id,
+        }
+    }
+}
+
+/// Replaces the title and description of a manifest command.
+///
+/// Hotkeys are still taken from the manifest command.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommandOverride {
+    /// ID of the command in the manifest.
+    pub manifest: CommandId,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Items to append to the end of a partial [`List`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ListExtension {
+    pub items: Vec<ListItem>,
+    /// Place section titles right **before** these indices.
+    ///
+    /// Indices are relative to the start of [`Self::items`], not the start of
+    /// the entire list.
+    pub section_titles: BTreeMap<usize, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -146,14 +853,101 @@ pub struct ListItem {
     pub description: String,
     pub icon: Option<ListItemIcon>,
     pub id: ActivationTarget,
-    pub commands: Vec<CommandId>,
+    pub commands: Vec<ListItemCommand>,
+    /// Identifies this item across different lists from the same plugin.
+    ///
+    /// Unlike [`Self::id`], this is chosen by the plugin and should stay the
+    /// same when the list is refreshed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Extra content to show while this item is selected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<ListItemPreview>,
+    /// Short text shown at the end of the item, like a shortcut or date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessory: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<ListItemTag>,
+    #[serde(default, skip_serializing_if = "ListItemHighlights::is_empty")]
+    pub highlights: ListItemHighlights,
+}
+
+/// Ranges of characters (not bytes) to emphasise, usually because they
+/// matched the query.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ListItemHighlights {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub title: Vec<Range<usize>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub description: Vec<Range<usize>>,
+}
+
+impl ListItemHighlights {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_empty() && self.description.is_empty()
+    }
+}
+
+/// A colored label shown at the end of a [`ListItem`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ListItemTag {
+    pub text: String,
+    #[serde(default)]
+    pub color: TagColor,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub enum ListItemIcon {
+    /// A freedesktop icon name, looked up in the user's icon themes.
     Name(String),
     Text(String),
+    /// Absolute path to an image file. Requires [`Feature::RichIcons`].
+    File(PathBuf),
+    /// Contents of an image file, such as a PNG or SVG. Requires
+    /// [`Feature::RichIcons`].
+    ///
+    /// Encoded as base64 in JSON.
+    Data(#[serde(with = "base64_bytes")] Arc<[u8]>),
+    /// Uses the first icon that can be found. Requires
+    /// [`Feature::RichIcons`].
+    Fallback(Vec<ListItemIcon>),
+}
+
+mod base64_bytes {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use serde::{Deserialize as _, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>, T: From<Vec<u8>>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let str = String::deserialize(deserializer)?;
+        STANDARD
+            .decode(str)
+            .map(T::from)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListItemPreview {
+    Markdown(String),
+    /// Shown in a monospace font.
+    Code {
+        text: String,
+        language: Option<String>,
+    },
+    /// Absolute path to an image file.
+    Image(PathBuf),
+    /// A table of key/value pairs.
+    Metadata(Vec<(String, String)>),
 }
 
 #[cfg(test)]
@@ -166,6 +960,8 @@ mod tests {
             id: RequestId(0),
             request: RequestBody::Query(RequestQuery {
                 text: "this is my query".to_owned(),
+                view: None,
+                context: None,
             }),
         }
         .serialize();
@@ -174,4 +970,75 @@ mod tests {
             r#"{"id":0,"request":{"query":{"text":"this is my query"}}}"#
         );
     }
+
+    #[test]
+    fn icon_data_base64() {
+        let icon = ListItemIcon::Data(Arc::from(*b"png"));
+        let json = serde_json::to_string(&icon).unwrap();
+        assert_eq!(json, r#"{"data":"cG5n"}"#);
+        assert_eq!(serde_json::from_str::<ListItemIcon>(&json).unwrap(), icon);
+    }
+
+    #[test]
+    fn runtime_commands() {
+        let commands: Vec<ListItemCommand> = serde_json::from_str(
+            r#"["activate", {"id":"open-with-vim","title":"Open with Vim","description":null,"default-hotkeys":["ctrl+v"]}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            commands[0],
+            ListItemCommand::Manifest(CommandId::new("activate"))
+        );
+        let ListItemCommand::Runtime(command) = &commands[1] else {
+            panic!("expected a runtime command, got {:?}", commands[1]);
+        };
+        assert_eq!(command.title, "Open with Vim");
+    }
+
+    #[test]
+    fn overridden_commands() {
+        let commands: Vec<ListItemCommand> = serde_json::from_str(
+            r#"[{"manifest":"activate","title":"Switch to Firefox"}, {"id":"activate","title":"Activate"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            commands[0],
+            ListItemCommand::Overridden(CommandOverride {
+                manifest: CommandId::new("activate"),
+                title: "Switch to Firefox".to_owned(),
+                description: None,
+            })
+        );
+        assert!(matches!(commands[1], ListItemCommand::Runtime(_)));
+    }
+
+    #[test]
+    fn log_record() {
+        let response: Response = serde_json::from_str(
+            r#"{"request-id":0,"response":{"log":{"level":"warn","target":"my_plugin","message":"slow","fields":{"ms":120}}}}"#,
+        )
+        .unwrap();
+        let ResponseBody::Log(record) = response.response else {
+            panic!("expected a log record");
+        };
+        assert_eq!(record.level, LogLevel::Warn);
+        assert_eq!(record.fields["ms"], serde_json::json!(120));
+    }
+
+    #[test]
+    fn unknown_features() {
+        let hello: Hello =
+            serde_json::from_str(r#"{"protocol-version":1,"features":["streaming-lists","new"]}"#)
+                .unwrap();
+        assert!(hello.supports(Feature::StreamingLists));
+        assert!(hello.supports(Feature::Unknown));
+    }
+
+    #[test]
+    fn older_protocol_versions_are_incompatible() {
+        let v1: Hello = serde_json::from_str(r#"{"protocol-version":1,"features":[]}"#).unwrap();
+        assert!(!Hello::current().is_compatible_with(&v1));
+        assert!(!v1.is_compatible_with(&Hello::current()));
+        assert!(Hello::current().is_compatible_with(&Hello::current()));
+    }
 }
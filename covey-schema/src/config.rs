@@ -34,23 +34,75 @@ pub struct AppSettings {
     /// Default is Ctrl+R.
     #[serde(default = "default_reload_hotkey")]
     pub reload_hotkey: Hotkey,
+    /// Hotkey to mark or unmark the selected list item, so that a command can
+    /// be activated on several items at once.
+    ///
+    /// Items can also be marked by clicking with Ctrl or Shift held.
+    ///
+    /// Default is Ctrl+M.
+    #[serde(default = "default_toggle_mark_hotkey")]
+    pub toggle_mark_hotkey: Hotkey,
     /// List of system icon themes to use when rendering a named icon from a
     /// plugin.
     ///
     /// Icons will try to be loaded from top to bottom.
     #[serde(default = "default_icon_themes")]
     pub icon_themes: Arc<[String]>,
+    /// Programs used to open URLs and paths requested by plugins.
+    #[serde(default)]
+    pub opener: OpenerSettings,
 }
 
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
             reload_hotkey: default_reload_hotkey(),
+            toggle_mark_hotkey: default_toggle_mark_hotkey(),
             icon_themes: default_icon_themes(),
+            opener: OpenerSettings::default(),
         }
     }
 }
 
+/// Commands used to open URLs and paths.
+///
+/// Each command is a list of the program and its arguments. Any `{}` in the
+/// arguments is replaced with the URL or path. If there is no `{}`, the URL or
+/// path is added as the last argument.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "ts-rs", derive(ts_rs::TS))]
+#[serde(rename_all = "kebab-case", default)]
+pub struct OpenerSettings {
+    /// Command used when no other handler matches.
+    ///
+    /// Default is `["xdg-open"]`.
+    #[serde(default = "default_opener_command")]
+    pub default: Vec<String>,
+    /// Commands for specific URL schemes, like `https` or `mailto`.
+    #[serde(default)]
+    pub schemes: BTreeMap<String, Vec<String>>,
+    /// Commands for paths of specific MIME types.
+    ///
+    /// Keys can be a full MIME type like `image/png` or a wildcard like
+    /// `text/*`. Directories have the MIME type `inode/directory`.
+    #[serde(default)]
+    pub mime_types: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for OpenerSettings {
+    fn default() -> Self {
+        Self {
+            default: default_opener_command(),
+            schemes: BTreeMap::new(),
+            mime_types: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_opener_command() -> Vec<String> {
+    vec![String::from("xdg-open")]
+}
+
 fn default_reload_hotkey() -> Hotkey {
     Hotkey {
         key: KeyCode::R,
@@ -61,6 +113,16 @@ fn default_reload_hotkey() -> Hotkey {
     }
 }
 
+fn default_toggle_mark_hotkey() -> Hotkey {
+    Hotkey {
+        key: KeyCode::M,
+        ctrl: true,
+        alt: false,
+        shift: false,
+        meta: false,
+    }
+}
+
 fn default_icon_themes() -> Arc<[String]> {
     Arc::from([String::from("hicolor")])
 }
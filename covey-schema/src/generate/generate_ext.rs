@@ -27,6 +27,31 @@ pub(super) fn generate_ext_trait(manifest: &PluginManifest, paths: &CratePaths)
             }
         })
         .collect();
+    let titled_signatures: Vec<_> = command_ids.iter()
+        .map(|command| {
+            let method = format_ident!("on_{}_with_title", command.replace('-', "_"));
+
+            quote! {
+                fn #method(
+                    self,
+                    title: impl ::core::convert::Into<#covey_plugin::CommandTitle>,
+                    callback: impl AsyncFn(&#covey_plugin::Menu) -> #covey_plugin::Result<()> + ::core::marker::Send + ::core::marker::Sync + 'static
+                ) -> Self
+            }
+        })
+        .collect();
+    let batch_signatures: Vec<_> = command_ids.iter()
+        .map(|command| {
+            let method = format_ident!("on_{}_batch", command.replace('-', "_"));
+
+            quote! {
+                fn #method(
+                    self,
+                    callback: impl AsyncFn(&#covey_plugin::Menu, ::std::vec::Vec<::std::string::String>) -> #covey_plugin::Result<()> + ::core::marker::Send + ::core::marker::Sync + 'static
+                ) -> Self
+            }
+        })
+        .collect();
 
     let menu_doclink = format!("[`Menu`]({covey_plugin}::Menu)");
     let display_error_doclink =
@@ -45,6 +70,12 @@ pub(super) fn generate_ext_trait(manifest: &PluginManifest, paths: &CratePaths)
                 #[doc = #display_error_doclink]
                 /// will be called on the error.
                 #signatures;
+
+                /// Same as the method without `_with_title`, but shows a
+                /// different title for the command on this target.
+                ///
+                /// Hotkeys are still taken from the manifest.
+                #titled_signatures;
             )*
         }
     };
@@ -58,6 +89,14 @@ pub(super) fn generate_ext_trait(manifest: &PluginManifest, paths: &CratePaths)
                         callback
                     )
                 }
+
+                #titled_signatures {
+                    self.add_command_with_title(
+                        #command_ids,
+                        title,
+                        callback
+                    )
+                }
             )*
         }
     };
@@ -71,6 +110,46 @@ pub(super) fn generate_ext_trait(manifest: &PluginManifest, paths: &CratePaths)
                         callback
                     )
                 }
+
+                #titled_signatures {
+                    self.add_command_with_title(
+                        #command_ids,
+                        title,
+                        callback
+                    )
+                }
+            )*
+        }
+    };
+
+    let key_doclink = format!("[`ListItem::key`]({covey_plugin}::ListItem::key)");
+
+    let batch_trait_def = quote! {
+        pub trait BatchCommandExt {
+            #(
+                /// Runs when this command is activated on several items of
+                /// this list at once.
+                ///
+                /// The closure takes in the
+                #[doc = #key_doclink]
+                /// of each activated item, so items need a key to be
+                /// activated together. Activated items without a key run
+                /// their own callback for this command instead, one at a
+                /// time after this closure.
+                #batch_signatures;
+            )*
+        }
+    };
+
+    let batch_list_impl = quote! {
+        impl self::BatchCommandExt for #covey_plugin::List {
+            #(
+                #batch_signatures {
+                    self.add_batch_command(
+                        #command_ids,
+                        callback
+                    )
+                }
             )*
         }
     };
@@ -80,5 +159,9 @@ pub(super) fn generate_ext_trait(manifest: &PluginManifest, paths: &CratePaths)
 
         #list_item_impl
         #list_impl
+
+        #batch_trait_def
+
+        #batch_list_impl
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use az::SaturatingAs;
 use hex_color::HexColor;
 use serde::{Deserialize, Serialize};
@@ -77,6 +79,26 @@ impl Padding {
     }
 }
 
+/// A color from the palette used for list item tags.
+///
+/// The actual color can be themed by [`UserStyle::tag_color`].
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TagColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    /// Also used for colors that aren't known by this version of covey.
+    #[default]
+    #[serde(other)]
+    Gray,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "kebab-case")]
 pub struct UserStyle {
@@ -105,6 +127,9 @@ pub struct UserStyle {
     list_item_bg: Option<Color>,
     list_item_hovered_bg: Option<Color>,
     list_item_active_bg: Option<Color>,
+    match_highlight_color: Option<Color>,
+    // preview of the selected list item, beside the list
+    preview_width: Option<f32>,
     // bottom info bar
     info_button_gap: Option<f32>,
     info_button_rounding: Option<f32>,
@@ -112,6 +137,9 @@ pub struct UserStyle {
     info_button_bg: Option<Color>,
     info_button_hovered_bg: Option<Color>,
     info_button_active_bg: Option<Color>,
+    // list item tags
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    tag_colors: BTreeMap<TagColor, Color>,
 }
 
 /// Getters with defaults.
@@ -198,6 +226,16 @@ impl UserStyle {
             .unwrap_or(Color::WHITE.multiply_alpha(0.2))
     }
 
+    /// Color of characters in a list item that matched the query.
+    pub fn match_highlight_color(&self) -> Color {
+        self.match_highlight_color
+            .unwrap_or(Color::rgb(240, 134, 164))
+    }
+
+    pub fn preview_width(&self) -> f32 {
+        self.preview_width.unwrap_or(self.inner_width() * 0.5)
+    }
+
     pub fn info_button_gap(&self) -> f32 {
         self.info_button_gap.unwrap_or(self.list_item_gap())
     }
@@ -226,6 +264,18 @@ impl UserStyle {
             .unwrap_or(Color::WHITE.multiply_alpha(0.2))
     }
 
+    pub fn tag_color(&self, color: TagColor) -> Color {
+        self.tag_colors.get(&color).copied().unwrap_or(match color {
+            TagColor::Red => Color::rgb(248, 113, 113),
+            TagColor::Orange => Color::rgb(251, 146, 60),
+            TagColor::Yellow => Color::rgb(250, 204, 21),
+            TagColor::Green => Color::rgb(74, 222, 128),
+            TagColor::Blue => Color::rgb(96, 165, 250),
+            TagColor::Purple => Color::rgb(192, 132, 252),
+            TagColor::Gray => Color::rgb(156, 163, 175),
+        })
+    }
+
     // TODO: this isn't completely accurate, the height should be the "row height".
     // but this can only be computed from egui with a known font.
     /// Computed property.
@@ -1,8 +1,8 @@
 //! Actions returned by a plugin.
 
-use std::{collections::BTreeMap, fmt, path::PathBuf};
+use std::{collections::BTreeMap, fmt, ops::Range, path::PathBuf, sync::Arc, time::Duration};
 
-use covey_schema::{hotkey::Hotkey, manifest::Command};
+use covey_schema::{hotkey::Hotkey, id::CommandId, manifest::Command, style::TagColor};
 
 use crate::{Host, Plugin};
 
@@ -18,9 +18,147 @@ pub(crate) enum Message {
 pub enum Action {
     Close,
     SetList(List),
-    Copy(String),
+    /// Appends items to the current list.
+    ///
+    /// Should be applied with [`List::extend`], which ignores extensions that
+    /// belong to a different list.
+    ExtendList(ListExtension),
+    /// Replaces the current list with a newer version of it.
+    ///
+    /// The list selection should be kept if possible.
+    UpdateList(List),
+    Copy(ClipboardContent),
     SetInput(Input),
     DisplayError(String, String),
+    Notify(Notification),
+    /// Ask the user a question, then answer it with [`Host::answer_prompt`].
+    Prompt(Prompt),
+    /// Show or update the progress of a running command.
+    Progress(Task, Progress),
+    /// Hide the progress of a command.
+    EndProgress(Task),
+    /// A new list has been shown on top of the current one.
+    ///
+    /// The input should be cleared and queried again. Go back with
+    /// [`Host::pop_view`].
+    PushView(View),
+}
+
+/// Content to put in the clipboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardContent {
+    Text(String),
+    /// HTML, with plain text for applications that can't paste HTML.
+    Html {
+        html: String,
+        alt_text: Option<String>,
+    },
+    /// Contents of an image file, such as a PNG.
+    Image(Arc<[u8]>),
+    /// Absolute paths of files, as copied by a file manager.
+    Files(Vec<PathBuf>),
+}
+
+/// A list pushed on top of a plugin's main list.
+#[derive(Debug, Clone)]
+pub struct View {
+    pub(crate) plugin: Plugin,
+    pub(crate) id: covey_proto::ViewId,
+    pub(crate) title: String,
+}
+
+impl View {
+    pub fn plugin(&self) -> &Plugin {
+        &self.plugin
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+}
+
+/// A command that is being run by a plugin.
+///
+/// Can be cancelled with [`Host::cancel_task`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub(crate) plugin: Plugin,
+    pub(crate) request_id: covey_proto::RequestId,
+}
+
+impl Task {
+    pub fn plugin(&self) -> &Plugin {
+        &self.plugin
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Between 0 and 1, or [`None`] if the progress is unknown.
+    pub fraction: Option<f32>,
+    pub message: String,
+}
+
+/// A message from a plugin that should be shown to the user.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub severity: Severity,
+    pub title: String,
+    pub body: String,
+    /// [`None`] if the frontend's default timeout should be used.
+    pub timeout: Option<Duration>,
+}
+
+/// A question from a plugin that should be shown to the user.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub(crate) plugin: Plugin,
+    pub(crate) id: covey_proto::PromptId,
+    pub(crate) title: String,
+    pub(crate) kind: PromptKind,
+}
+
+impl Prompt {
+    pub fn plugin(&self) -> &Plugin {
+        &self.plugin
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn kind(&self) -> &PromptKind {
+        &self.kind
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PromptKind {
+    /// A single line of text, answered with [`PromptAnswer::Text`].
+    Text {
+        placeholder: String,
+        initial: String,
+    },
+    /// One of several options, answered with [`PromptAnswer::Choice`].
+    Choice(Vec<String>),
+    /// A yes/no question, answered with [`PromptAnswer::Confirm`].
+    Confirm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptAnswer {
+    Text(String),
+    /// Index of the chosen option.
+    Choice(usize),
+    Confirm(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
 }
 
 /// The main text input contents and selection.
@@ -37,6 +175,8 @@ pub struct List {
     pub(crate) section_titles: BTreeMap<usize, String>,
     pub(crate) activation_target: ActivationTarget,
     pub(crate) request_id: covey_proto::RequestId,
+    pub(crate) is_complete: bool,
+    pub(crate) selection: Option<covey_proto::ListSelection>,
 }
 
 impl List {
@@ -56,6 +196,25 @@ impl List {
         self.section_titles.get(&idx).map(String::as_str)
     }
 
+    /// Index of the first item with the provided [`ListItem::key`].
+    pub fn position_of_key(&self, key: &str) -> Option<usize> {
+        self.items.iter().position(|item| item.key() == Some(key))
+    }
+
+    /// Index of the item that the plugin wants to select when this list is
+    /// shown.
+    ///
+    /// If this is [`None`], the item with the same key as the previously
+    /// selected item should be selected.
+    pub fn requested_selection(&self) -> Option<usize> {
+        match self.selection.as_ref()? {
+            covey_proto::ListSelection::Index(index) => {
+                Some((*index).min(self.len().saturating_sub(1)))
+            }
+            covey_proto::ListSelection::Key(key) => self.position_of_key(key),
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -71,6 +230,35 @@ impl List {
     pub fn activation_target(&self) -> &ActivationTarget {
         &self.activation_target
     }
+
+    /// Whether the plugin has finished sending items for this list.
+    ///
+    /// Incomplete lists will have more items added by
+    /// [`Action::ExtendList`].
+    pub fn is_complete(&self) -> bool {
+        self.is_complete
+    }
+
+    /// Appends the items of an extension to the end of this list.
+    ///
+    /// Returns `false` and does nothing if the extension is for a different
+    /// list.
+    pub fn extend(&mut self, extension: ListExtension) -> bool {
+        if extension.request_id != self.request_id || self.is_complete {
+            return false;
+        }
+
+        let offset = self.items.len();
+        self.section_titles.extend(
+            extension
+                .section_titles
+                .into_iter()
+                .map(|(idx, title)| (idx + offset, title)),
+        );
+        self.items.extend(extension.items);
+        self.is_complete = extension.is_final;
+        true
+    }
 }
 
 impl fmt::Debug for List {
@@ -80,6 +268,39 @@ impl fmt::Debug for List {
             .field("section_titles", &self.section_titles)
             .field("activation_target", &self.activation_target)
             .field("request_id", &self.request_id)
+            .field("is_complete", &self.is_complete)
+            .field("selection", &self.selection)
+            .finish()
+    }
+}
+
+/// More items to add to an incomplete [`List`].
+pub struct ListExtension {
+    pub(crate) items: Vec<ListItem>,
+    /// Relative to the start of [`Self::items`].
+    pub(crate) section_titles: BTreeMap<usize, String>,
+    pub(crate) request_id: covey_proto::RequestId,
+    /// Whether this is the last extension of the list.
+    pub(crate) is_final: bool,
+}
+
+impl ListExtension {
+    pub fn items(&self) -> &[ListItem] {
+        &self.items
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.is_final
+    }
+}
+
+impl fmt::Debug for ListExtension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListExtension")
+            .field("items", &&self.items[..3.min(self.items.len())])
+            .field("section_titles", &self.section_titles)
+            .field("request_id", &self.request_id)
+            .field("is_final", &self.is_final)
             .finish()
     }
 }
@@ -90,6 +311,11 @@ pub struct ListItem {
     pub(crate) title: String,
     pub(crate) description: String,
     pub(crate) icon: Option<Icon>,
+    pub(crate) preview: Option<Preview>,
+    pub(crate) accessory: Option<String>,
+    pub(crate) tags: Vec<Tag>,
+    pub(crate) highlights: covey_proto::ListItemHighlights,
+    pub(crate) key: Option<String>,
     pub(crate) activation_target: ActivationTarget,
 }
 
@@ -106,42 +332,134 @@ impl ListItem {
         &self.description
     }
 
+    /// Identifies this item across lists from the same plugin.
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
     pub fn icon(&self) -> Option<&Icon> {
         self.icon.as_ref()
     }
 
+    /// Extra content to show while this item is selected.
+    pub fn preview(&self) -> Option<&Preview> {
+        self.preview.as_ref()
+    }
+
+    /// Short text to show at the end of the item.
+    pub fn accessory(&self) -> Option<&str> {
+        self.accessory.as_deref()
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// Ranges of characters (not bytes) in the title to emphasise.
+    pub fn title_highlights(&self) -> &[Range<usize>] {
+        &self.highlights.title
+    }
+
+    /// Ranges of characters (not bytes) in the description to emphasise.
+    pub fn description_highlights(&self) -> &[Range<usize>] {
+        &self.highlights.description
+    }
+
     pub fn activation_target(&self) -> &ActivationTarget {
         &self.activation_target
     }
 }
 
+/// A colored label to show at the end of a [`ListItem`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub(crate) text: String,
+    pub(crate) color: TagColor,
+}
+
+impl Tag {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Should be converted to an actual color with
+    /// [`UserStyle::tag_color`](covey_schema::style::UserStyle::tag_color).
+    pub fn color(&self) -> TagColor {
+        self.color
+    }
+}
+
+/// Detailed content about a [`ListItem`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Preview {
+    Markdown(String),
+    /// Should be shown in a monospace font.
+    Code {
+        text: String,
+        language: Option<String>,
+    },
+    /// Path to an image file.
+    Image(PathBuf),
+    /// A table of key/value pairs.
+    Metadata(Vec<(String, String)>),
+}
+
 #[derive(Debug, Clone)]
 pub struct ActivationTarget {
     pub(crate) plugin: Plugin,
     /// ID unique within the plugin.
     pub(crate) local_target_id: covey_proto::ActivationTarget,
+    /// Available commands that are declared in the plugin's manifest.
     pub(crate) commands: Vec<covey_proto::CommandId>,
+    /// Manifest commands with a different title and description for this
+    /// target.
+    pub(crate) overridden_commands: Vec<Command>,
+    /// Available commands that the plugin created at runtime.
+    pub(crate) runtime_commands: Vec<Command>,
 }
 
 impl ActivationTarget {
     /// The commands that can be activated on this list item as reported by the
     /// plugin.
     ///
-    /// Commands will be given in the returned iterator in the same order as the
-    /// the commands are defined in the plugin's manifest.
+    /// Commands from the manifest are given first, in the same order as they
+    /// are defined in the plugin's manifest. The plugin may have replaced
+    /// their title and description. Commands created at runtime follow in the
+    /// order given by the plugin.
     pub fn available_commands(&self) -> impl Iterator<Item = &Command> {
         self.plugin
             .manifest()
             .commands
             .iter()
             .filter(|cmd| self.commands.contains(&cmd.id))
+            .map(|cmd| {
+                self.overridden_commands
+                    .iter()
+                    .find(|overridden| overridden.id == cmd.id)
+                    .unwrap_or(cmd)
+            })
+            .chain(&self.runtime_commands)
+    }
+
+    /// Get the hotkeys of an available command.
+    ///
+    /// Same as [`Plugin::hotkeys_of_cmd`], but also includes the default
+    /// hotkeys of runtime commands.
+    pub fn hotkeys_of_cmd(&self, cmd_id: &CommandId) -> Option<&[Hotkey]> {
+        self.plugin.hotkeys_of_cmd(cmd_id).or_else(|| {
+            self.runtime_commands
+                .iter()
+                .find(|cmd| cmd.id == *cmd_id)?
+                .default_hotkeys
+                .as_deref()
+        })
     }
 
     /// Gets the command that can be activated from the provided hotkey.
     pub fn activated_command_from_hotkey(&self, hotkey: Hotkey) -> Option<&Command> {
         self.available_commands().find(|cmd| {
-            self.plugin
-                .hotkeys_of_cmd(&cmd.id)
+            self.hotkeys_of_cmd(&cmd.id)
                 .is_some_and(|hotkeys| hotkeys.contains(&hotkey))
         })
     }
@@ -152,12 +470,27 @@ impl ActivationTarget {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Icon(pub(crate) covey_proto::ListItemIcon);
+pub struct Icon(pub(crate) IconKind);
+
+/// A [`covey_proto::ListItemIcon`] with extra information computed once
+/// when it is received, so that resolving it is cheap.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum IconKind {
+    Name(String),
+    Text(String),
+    File(PathBuf),
+    Data {
+        /// A URI that is unique to these bytes.
+        uri: String,
+        bytes: Arc<[u8]>,
+    },
+    Fallback(Vec<IconKind>),
+}
 
 impl Icon {
     /// New icon that will be searched in the file system.
     pub fn new_named(name: String) -> Self {
-        Self(covey_proto::ListItemIcon::Name(name))
+        Self(IconKind::Name(name))
     }
 
     pub fn resolve(&self, host: &Host) -> Result<ResolvedIcon, ResolveIconError> {
@@ -182,9 +515,16 @@ impl fmt::Display for ResolveIconError {
 
 impl std::error::Error for ResolveIconError {}
 
-/// Icon with named system icons resolved to a file path.
+/// Icon with named system icons resolved to a file path and fallbacks
+/// resolved to a single icon.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ResolvedIcon {
     File(PathBuf),
     Text(String),
+    /// Contents of an image file.
+    Bytes {
+        /// A URI that is unique to these bytes.
+        uri: String,
+        bytes: Arc<[u8]>,
+    },
 }
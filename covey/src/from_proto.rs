@@ -1,16 +1,19 @@
-use std::ops::Range;
+use std::{
+    hash::{Hash as _, Hasher as _},
+    ops::Range,
+    time::Duration,
+};
 
-use crate::Plugin;
+use crate::{Plugin, event::IconKind};
 
-pub(crate) fn input(input: covey_proto::Input, plugin: &Plugin) -> crate::Input {
+/// `prefix` should be the plugin's prefix, or empty if the input was set from
+/// a view.
+pub(crate) fn input(input: covey_proto::Input, prefix: &str) -> crate::Input {
     let covey_proto::Input {
         mut query,
         selection: Range { start, end },
     } = input;
 
-    let prefix = plugin
-        .prefix()
-        .expect("plugin with no prefix should never be queried");
     let prefix_len = prefix.chars().count();
 
     query.insert_str(0, prefix);
@@ -28,12 +31,14 @@ pub(crate) fn list(
     list: covey_proto::List,
     plugin: &Plugin,
     request_id: covey_proto::RequestId,
+    is_complete: bool,
 ) -> crate::List {
     let covey_proto::List {
         id,
         items,
         section_titles,
         commands: list_commands,
+        selection,
     } = list;
 
     crate::List {
@@ -43,11 +48,70 @@ pub(crate) fn list(
             .collect(),
         section_titles,
         request_id,
-        activation_target: crate::ActivationTarget {
-            plugin: plugin.clone(),
-            local_target_id: id,
-            commands: list_commands,
-        },
+        activation_target: self::activation_target(plugin, id, list_commands),
+        is_complete,
+        selection,
+    }
+}
+
+pub(crate) fn list_extension(
+    extension: covey_proto::ListExtension,
+    plugin: &Plugin,
+    request_id: covey_proto::RequestId,
+) -> crate::ListExtension {
+    let covey_proto::ListExtension {
+        items,
+        section_titles,
+    } = extension;
+
+    crate::ListExtension {
+        items: items
+            .into_iter()
+            .map(|item| self::list_item(item, plugin))
+            .collect(),
+        section_titles,
+        request_id,
+        is_final: false,
+    }
+}
+
+fn activation_target(
+    plugin: &Plugin,
+    id: covey_proto::ActivationTarget,
+    commands: Vec<covey_proto::ListItemCommand>,
+) -> crate::ActivationTarget {
+    let mut manifest_commands = vec![];
+    let mut overridden_commands = vec![];
+    let mut runtime_commands = vec![];
+    for command in commands {
+        match command {
+            covey_proto::ListItemCommand::Manifest(id) => manifest_commands.push(id),
+            covey_proto::ListItemCommand::Overridden(command) => {
+                let Some(manifest_command) = plugin
+                    .manifest()
+                    .commands
+                    .iter()
+                    .find(|cmd| cmd.id == command.manifest)
+                else {
+                    continue;
+                };
+                overridden_commands.push(covey_schema::manifest::Command {
+                    title: command.title,
+                    description: command.description,
+                    ..manifest_command.clone()
+                });
+                manifest_commands.push(command.manifest);
+            }
+            covey_proto::ListItemCommand::Runtime(command) => runtime_commands.push(command),
+        }
+    }
+
+    crate::ActivationTarget {
+        plugin: plugin.clone(),
+        local_target_id: id,
+        commands: manifest_commands,
+        overridden_commands,
+        runtime_commands,
     }
 }
 
@@ -58,20 +122,126 @@ fn list_item(item: covey_proto::ListItem, plugin: &Plugin) -> crate::ListItem {
         icon,
         id,
         commands: item_commands,
+        key,
+        preview,
+        accessory,
+        tags,
+        highlights,
     } = item;
 
     crate::ListItem {
-        activation_target: crate::ActivationTarget {
-            plugin: plugin.clone(),
-            local_target_id: id,
-            commands: item_commands,
-        },
+        activation_target: self::activation_target(plugin, id, item_commands),
         icon: icon.map(self::icon),
+        preview: preview.map(self::preview),
+        accessory,
+        tags: tags.into_iter().map(self::tag).collect(),
+        highlights,
+        key,
         title,
         description,
     }
 }
 
 fn icon(proto: covey_proto::ListItemIcon) -> crate::Icon {
-    crate::Icon(proto)
+    crate::Icon(icon_kind(proto))
+}
+
+fn icon_kind(proto: covey_proto::ListItemIcon) -> IconKind {
+    match proto {
+        covey_proto::ListItemIcon::Name(name) => IconKind::Name(name),
+        covey_proto::ListItemIcon::Text(text) => IconKind::Text(text),
+        covey_proto::ListItemIcon::File(path) => IconKind::File(path),
+        covey_proto::ListItemIcon::Data(bytes) => IconKind::Data {
+            uri: bytes_icon_uri(&bytes),
+            bytes,
+        },
+        covey_proto::ListItemIcon::Fallback(icons) => {
+            IconKind::Fallback(icons.into_iter().map(icon_kind).collect())
+        }
+    }
+}
+
+/// A unique URI for the contents of an icon, used by frontends to cache the
+/// decoded image.
+fn bytes_icon_uri(bytes: &[u8]) -> String {
+    let mut hasher = std::hash::DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    // SVGs can't be detected from their contents, add an extension.
+    let text_start = String::from_utf8_lossy(&bytes[..bytes.len().min(256)]);
+    if text_start.contains("<svg") {
+        format!("bytes://covey-icon-{hash:016x}.svg")
+    } else {
+        format!("bytes://covey-icon-{hash:016x}")
+    }
+}
+
+fn preview(proto: covey_proto::ListItemPreview) -> crate::Preview {
+    match proto {
+        covey_proto::ListItemPreview::Markdown(text) => crate::Preview::Markdown(text),
+        covey_proto::ListItemPreview::Code { text, language } => {
+            crate::Preview::Code { text, language }
+        }
+        covey_proto::ListItemPreview::Image(path) => crate::Preview::Image(path),
+        covey_proto::ListItemPreview::Metadata(entries) => crate::Preview::Metadata(entries),
+    }
+}
+
+fn tag(proto: covey_proto::ListItemTag) -> crate::Tag {
+    let covey_proto::ListItemTag { text, color } = proto;
+    crate::Tag { text, color }
+}
+
+pub(crate) fn clipboard_content(content: covey_proto::ClipboardContent) -> crate::ClipboardContent {
+    match content {
+        covey_proto::ClipboardContent::Text(text) => crate::ClipboardContent::Text(text),
+        covey_proto::ClipboardContent::Html { html, alt_text } => {
+            crate::ClipboardContent::Html { html, alt_text }
+        }
+        covey_proto::ClipboardContent::Image(bytes) => crate::ClipboardContent::Image(bytes),
+        covey_proto::ClipboardContent::Files(paths) => crate::ClipboardContent::Files(paths),
+    }
+}
+
+pub(crate) fn notification(notification: covey_proto::Notification) -> crate::Notification {
+    let covey_proto::Notification {
+        severity,
+        title,
+        body,
+        timeout_ms,
+    } = notification;
+
+    crate::Notification {
+        severity: match severity {
+            covey_proto::Severity::Info => crate::Severity::Info,
+            covey_proto::Severity::Success => crate::Severity::Success,
+            covey_proto::Severity::Warning => crate::Severity::Warning,
+            covey_proto::Severity::Error => crate::Severity::Error,
+        },
+        title,
+        body,
+        timeout: timeout_ms.map(Duration::from_millis),
+    }
+}
+
+pub(crate) fn prompt(prompt: covey_proto::Prompt, plugin: &Plugin) -> crate::Prompt {
+    let covey_proto::Prompt { id, title, kind } = prompt;
+
+    crate::Prompt {
+        plugin: plugin.clone(),
+        id,
+        title,
+        kind: match kind {
+            covey_proto::PromptKind::Text {
+                placeholder,
+                initial,
+            } => crate::PromptKind::Text {
+                placeholder,
+                initial,
+            },
+            covey_proto::PromptKind::Choice { options } => crate::PromptKind::Choice(options),
+            covey_proto::PromptKind::Confirm => crate::PromptKind::Confirm,
+        },
+    }
 }
@@ -7,11 +7,14 @@
 //! consume with [`Self::recv_action`].
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
     io::{Read as _, Write as _},
     path::PathBuf,
-    sync::{Arc, Mutex, atomic::AtomicBool},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
     thread,
     time::{Duration, Instant},
 };
@@ -22,15 +25,30 @@ use covey_schema::{
     hotkey::Hotkey,
     id::{CommandId, PluginId},
     keyed_list::KeyedList,
+    style::UserStyle,
 };
 use futures::channel::mpsc;
 use tracing::{debug, error, info, warn};
 
 use crate::{
-    Action, ActivationTarget, CONFIG_DIR, CONFIG_PATH, Icon, PLUGINS_DIR, Plugin, ResolveIconError,
-    ResolvedIcon, cache::Cache, event::Message, plugin::PluginWeak,
+    Action, ActivationTarget, CONFIG_DIR, CONFIG_PATH, ClipboardContent, Icon, PLUGINS_DIR, Plugin,
+    Progress, Prompt, PromptAnswer, ResolveIconError, ResolvedIcon, Task, View,
+    cache::Cache,
+    event::{IconKind, Message},
+    opener::Opener,
+    plugin::PluginWeak,
+    view::ViewStack,
 };
 
+/// Extra information about a query from the frontend.
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// Position of the cursor in the query, in characters.
+    pub cursor: Option<usize>,
+    /// Whether this is the first query since the window was opened.
+    pub is_first: bool,
+}
+
 pub fn channel() -> Result<(Host, ActionReceiver)> {
     info!("reading config from file: {:?}", &*CONFIG_PATH);
 
@@ -54,6 +72,9 @@ pub fn channel() -> Result<(Host, ActionReceiver)> {
     info!("found plugins: {plugins:?}");
 
     let icon_themes = Arc::clone(&global_config.app.icon_themes);
+    let opener = Opener::new(global_config.app.opener.clone(), tx.clone());
+    let views = ViewStack::default();
+    let latest_sent_query = LatestQuery::default();
 
     Ok((
         Host {
@@ -62,14 +83,21 @@ pub fn channel() -> Result<(Host, ActionReceiver)> {
             plugins,
             // must be greater than the initial `latest_received_query_request_id`
             next_request_id: 1,
-            latest_sent_query_request_id: covey_proto::RequestId(0),
+            latest_sent_query: latest_sent_query.clone(),
+            latest_queried_plugin: None,
             // TODO: make this configurable
             plugin_process_gc: PluginProcessGc::new(Duration::from_hours(24)),
             icon_cache: Cache::new(move |name: &String| find_system_icon(name, &icon_themes)),
+            icon_file_cache: Cache::new(|path: &PathBuf| path.is_file()),
+            opener: opener.clone(),
+            views: views.clone(),
         },
         ActionReceiver {
             messages: rx,
             latest_received_query_request_id: 0,
+            latest_sent_query,
+            opener,
+            views,
         },
     ))
 }
@@ -79,36 +107,74 @@ pub struct Host {
     messages: mpsc::UnboundedSender<Message>,
     plugins: KeyedList<Plugin>,
     next_request_id: u64,
-    latest_sent_query_request_id: covey_proto::RequestId,
+    latest_sent_query: LatestQuery,
+    /// Plugin that received the latest query.
+    latest_queried_plugin: Option<PluginWeak>,
     plugin_process_gc: PluginProcessGc,
     /// Map from icon name to resolved path. Value is [`None`] if resolving
     /// failed.
     icon_cache: Cache<String, Option<PathBuf>>,
+    /// Map from icon file path to whether the file exists.
+    icon_file_cache: Cache<PathBuf, bool>,
+    opener: Opener,
+    views: ViewStack,
 }
 
 impl Host {
     /// Calls a plugin with this query.
     ///
     /// Responses should be handled by calling [`ActionReceiver::recv`].
-    #[tracing::instrument(skip(self))]
     pub fn send_query(&mut self, query: String) {
+        self.send_query_with(query, QueryOptions::default());
+    }
+
+    /// Same as [`Self::send_query`], but with more information for the
+    /// plugin.
+    #[tracing::instrument(skip(self))]
+    pub fn send_query_with(&mut self, query: String, options: QueryOptions) {
         debug!("setting input to {query:?}");
 
         let request_id = covey_proto::RequestId(self.next_request_id);
-        self.latest_sent_query_request_id = request_id;
+        let superseded_request_id = self.latest_sent_query.replace(request_id);
         self.next_request_id += 1;
 
-        let plugin_with_prefix = self
-            .plugins
-            .iter()
-            .filter(|plugin| !plugin.config_entry().disabled)
-            .find_map(|plugin| Some((plugin, query.strip_prefix(plugin.prefix()?)?)));
+        self.views.set_latest_query(&query);
+
+        // The top view gets the whole query, otherwise find the plugin with
+        // a matching prefix.
+        let target = match self.views.top() {
+            Some(view) => Some((view.plugin, query.clone(), Some(view.id), "")),
+            None => self
+                .plugins
+                .iter()
+                .filter(|plugin| !plugin.config_entry().disabled)
+                .find_map(|plugin| {
+                    let prefix = plugin.prefix()?;
+                    let stripped_query = query.strip_prefix(prefix)?;
+                    Some((plugin.clone(), stripped_query.to_owned(), None, prefix))
+                }),
+        };
 
-        match plugin_with_prefix {
-            Some((plugin, stripped_query)) => {
+        match target {
+            Some((plugin, text, view, prefix)) => {
                 tracing::debug!("querying plugin {plugin:?}");
-                self.plugin_process_gc.touch(plugin);
-                plugin.query(request_id, stripped_query.to_owned());
+                // The previous query's results will never be shown, stop the
+                // plugin from doing any more work on it.
+                if let Some(superseded_plugin) = self
+                    .latest_queried_plugin
+                    .replace(plugin.downgrade())
+                    .and_then(|plugin| plugin.upgrade())
+                {
+                    superseded_plugin.cancel(superseded_request_id);
+                }
+                self.plugin_process_gc.touch(&plugin);
+                let context = covey_proto::QueryContext {
+                    prefix: prefix.to_owned(),
+                    raw: query,
+                    cursor: options.cursor,
+                    is_first: options.is_first,
+                };
+                plugin.query(request_id, text, view, context);
             }
             None => {
                 tracing::warn!("no plugin activated with query {query}");
@@ -133,6 +199,38 @@ impl Host {
             .activate(request_id, target.local_target_id, command_id.clone())
     }
 
+    /// Activates several list items with the same command.
+    ///
+    /// All targets should be from the same list. If the plugin doesn't
+    /// support batch activation, each target is activated separately.
+    #[tracing::instrument(skip(self, targets))]
+    pub fn activate_many(&mut self, targets: &[&ActivationTarget], command_id: &CommandId) {
+        let Some(first) = targets.first() else { return };
+        let plugin = first.plugin();
+        debug_assert!(targets.iter().all(|target| target.plugin() == plugin));
+
+        if !plugin.supports(covey_proto::Feature::BatchActivation) {
+            for target in targets {
+                self.activate(target, command_id);
+            }
+            return;
+        }
+        debug!("activating {} targets", targets.len());
+
+        let request_id = covey_proto::RequestId(self.next_request_id);
+        self.next_request_id += 1;
+
+        self.plugin_process_gc.touch(plugin);
+        plugin.activate_many(
+            request_id,
+            targets
+                .iter()
+                .map(|target| target.local_target_id)
+                .collect(),
+            command_id.clone(),
+        )
+    }
+
     /// Activates a list item using the specified hotkey.
     ///
     /// Figures out the command to run based on the hotkey and plugin
@@ -149,22 +247,105 @@ impl Host {
         Some(command.id.clone())
     }
 
+    /// Sends the user's answer to a [`Action::Prompt`].
+    ///
+    /// `answer` should be [`None`] if the user dismissed the prompt. Every
+    /// prompt should be answered exactly once.
+    #[tracing::instrument(skip(self))]
+    pub fn answer_prompt(&mut self, prompt: &Prompt, answer: Option<PromptAnswer>) {
+        debug!("answering prompt {:?}", prompt.id);
+
+        let answer = answer.map(|answer| match answer {
+            PromptAnswer::Text(text) => covey_proto::PromptAnswer::Text(text),
+            PromptAnswer::Choice(idx) => covey_proto::PromptAnswer::Choice(idx),
+            PromptAnswer::Confirm(yes) => covey_proto::PromptAnswer::Confirm(yes),
+        });
+        self.plugin_process_gc.touch(prompt.plugin());
+        prompt.plugin().answer_prompt(prompt.id, answer);
+    }
+
+    /// Stops a running command.
+    ///
+    /// The plugin won't send an [`Action::EndProgress`] for a cancelled task.
+    #[tracing::instrument(skip(self))]
+    pub fn cancel_task(&mut self, task: &Task) {
+        debug!("cancelling task {:?}", task.request_id);
+        task.plugin().cancel(task.request_id);
+    }
+
+    /// Views that have been pushed, from bottom to top.
+    pub fn views(&self) -> Vec<View> {
+        self.views.views()
+    }
+
+    /// Goes back to the list before the top view.
+    ///
+    /// Returns the input that was shown before the view was pushed, or
+    /// [`None`] if there are no views. The input should be set to this and
+    /// queried again.
+    #[tracing::instrument(skip(self))]
+    pub fn pop_view(&mut self) -> Option<String> {
+        let (view, input) = self.views.pop()?;
+        debug!("popping view {:?}", view.title);
+        view.plugin.close_view(view.id);
+        Some(input)
+    }
+
     pub fn config(&self) -> &GlobalConfig {
         &self.config
     }
 
+    /// Tells running plugins that the window has been opened.
+    pub fn window_shown(&self) {
+        self.send_lifecycle(covey_proto::LifecycleEvent::WindowShown);
+    }
+
+    /// Tells running plugins that the window has been closed.
+    pub fn window_hidden(&self) {
+        self.send_lifecycle(covey_proto::LifecycleEvent::WindowHidden);
+    }
+
+    fn send_lifecycle(&self, event: covey_proto::LifecycleEvent) {
+        for plugin in &self.plugins {
+            plugin.send_lifecycle(event);
+        }
+    }
+
     /// Reloads all plugins with the new configuration.
     ///
+    /// Plugin processes are kept running if only their settings changed and
+    /// the plugin can apply the new settings. Use [`Self::reload_plugin`] to
+    /// restart a plugin after it has been rebuilt.
+    ///
     /// Should re-send a query immediately after reloading.
     #[tracing::instrument(skip_all)]
     pub fn reload(&mut self, config: GlobalConfig) {
         debug!("reloading");
         self.config = config;
-        self.plugins = load_plugins_from_config(&self.config, &self.messages);
+
+        // Views belong to the old plugins. Close them so that processes that
+        // are kept can drop their state.
+        for view in self.views.views() {
+            view.plugin.close_view(view.id);
+        }
+        self.views.clear();
+
+        let old_plugins = std::mem::replace(
+            &mut self.plugins,
+            load_plugins_from_config(&self.config, &self.messages),
+        );
+        for plugin in &self.plugins {
+            if let Some(old) = old_plugins.get(plugin.id()) {
+                reuse_process(old, plugin);
+            }
+        }
+        shutdown_in_background(old_plugins.into_iter().collect());
 
         let icon_themes = Arc::clone(&self.config.app.icon_themes);
         self.icon_cache
             .clear(move |name| find_system_icon(name, &icon_themes));
+        self.icon_file_cache.clear(|path| path.is_file());
+        self.opener.set_settings(self.config.app.opener.clone());
 
         // TODO: spawn this in another task and handle errors properly
         Self::write_config(&self.config).expect("TODO");
@@ -176,8 +357,23 @@ impl Host {
     pub fn reload_plugin(&mut self, plugin_id: &PluginId) {
         debug!("reloading plugin {plugin_id}");
 
+        // The plugin's views won't exist in the new process.
+        if self
+            .views
+            .views()
+            .iter()
+            .any(|view| view.plugin.id() == plugin_id)
+        {
+            self.views.clear();
+        }
+
+        let old_plugin = self.plugins.get(plugin_id).cloned();
         let replace_result = self.plugins.replace(plugin_id, |plugin| {
-            Plugin::new_read_manifest(plugin.config_entry().clone(), self.messages.clone())
+            Plugin::new_read_manifest(
+                plugin.config_entry().clone(),
+                theme(&self.config.style),
+                self.messages.clone(),
+            )
         });
 
         match replace_result {
@@ -193,7 +389,9 @@ impl Host {
             covey_schema::keyed_list::ReplaceResult::DifferentId => {
                 panic!("reloaded plugin should have same plugin id");
             }
-            covey_schema::keyed_list::ReplaceResult::Replaced => {}
+            covey_schema::keyed_list::ReplaceResult::Replaced => {
+                shutdown_in_background(old_plugin.into_iter().collect());
+            }
         }
     }
 
@@ -222,9 +420,21 @@ impl Host {
     }
 }
 
+impl Drop for Host {
+    /// Gives plugins a chance to save their state before they are killed.
+    fn drop(&mut self) {
+        crate::plugin::shutdown_processes(&self.plugins);
+    }
+}
+
 pub struct ActionReceiver {
     messages: mpsc::UnboundedReceiver<Message>,
     latest_received_query_request_id: u64,
+    /// Shared with [`Host`] to drop list updates of queries the user has
+    /// already left.
+    latest_sent_query: LatestQuery,
+    opener: Opener,
+    views: ViewStack,
 }
 
 impl ActionReceiver {
@@ -272,32 +482,147 @@ impl ActionReceiver {
         tracing::trace!(?plugin, ?response, "received plugin response");
 
         match response.response {
+            covey_proto::ResponseBody::Hello(_)
+            | covey_proto::ResponseBody::Log(_)
+            | covey_proto::ResponseBody::Storage(_) => {
+                // Handled by the plugin process reader.
+                tracing::warn!("ignoring unexpected response from plugin {}", plugin.id());
+                None
+            }
             covey_proto::ResponseBody::SetList(list) => {
-                // Check if the latest received id < new id. If so, send the action.
-                // Otherwise, this response is outdated and we should not update the list.
-                let new = response.request_id.0;
-                if self.latest_received_query_request_id < new {
-                    self.latest_received_query_request_id = new;
-                    Some(Action::SetList(crate::from_proto::list(
+                self.set_list(plugin, response.request_id, list, true)
+            }
+            covey_proto::ResponseBody::SetPartialList(list) => {
+                self.set_list(plugin, response.request_id, list, false)
+            }
+            covey_proto::ResponseBody::ExtendList(extension) => {
+                // Extensions are only useful for the list currently being shown.
+                if self.latest_received_query_request_id == response.request_id.0 {
+                    Some(Action::ExtendList(crate::from_proto::list_extension(
+                        extension,
+                        plugin,
+                        response.request_id,
+                    )))
+                } else {
+                    tracing::trace!("ignoring list extension due to outdated request id");
+                    None
+                }
+            }
+            covey_proto::ResponseBody::UpdateList(list) => {
+                // Only the list currently being shown can be updated.
+                if self.latest_received_query_request_id == response.request_id.0
+                    && self.latest_sent_query.is(response.request_id)
+                {
+                    Some(Action::UpdateList(crate::from_proto::list(
                         list,
                         plugin,
                         response.request_id,
+                        true,
                     )))
                 } else {
-                    tracing::trace!("ignoring list response due to outdated request id");
+                    tracing::trace!("ignoring list update due to outdated request id");
                     None
                 }
             }
-            covey_proto::ResponseBody::PerformAction(action) => Some(match action {
-                covey_proto::PluginAction::Close => Action::Close,
-                covey_proto::PluginAction::Copy(str) => Action::Copy(str),
+            covey_proto::ResponseBody::FinishList => {
+                if self.latest_received_query_request_id == response.request_id.0 {
+                    Some(Action::ExtendList(crate::ListExtension {
+                        items: vec![],
+                        section_titles: BTreeMap::new(),
+                        request_id: response.request_id,
+                        is_final: true,
+                    }))
+                } else {
+                    tracing::trace!("ignoring list completion due to outdated request id");
+                    None
+                }
+            }
+            covey_proto::ResponseBody::PerformAction(action) => match action {
+                covey_proto::PluginAction::Close => Some(Action::Close),
+                covey_proto::PluginAction::Copy(str) => {
+                    Some(Action::Copy(ClipboardContent::Text(str)))
+                }
+                covey_proto::PluginAction::CopyContent(content) => {
+                    Some(Action::Copy(crate::from_proto::clipboard_content(content)))
+                }
                 covey_proto::PluginAction::SetInput(input) => {
-                    Action::SetInput(crate::from_proto::input(input, plugin))
+                    // Views are queried without a prefix.
+                    let prefix = if self.views.top_is_from(plugin) {
+                        ""
+                    } else {
+                        plugin
+                            .prefix()
+                            .expect("plugin with no prefix should never be queried")
+                    };
+                    Some(Action::SetInput(crate::from_proto::input(input, prefix)))
                 }
-                covey_proto::PluginAction::DisplayError(err) => {
-                    Action::DisplayError(format!("Plugin {} failed", plugin.id()), err)
+                covey_proto::PluginAction::DisplayError(err) => Some(Action::DisplayError(
+                    format!("Plugin {} failed", plugin.id()),
+                    err,
+                )),
+                covey_proto::PluginAction::Notify(notification) => Some(Action::Notify(
+                    crate::from_proto::notification(notification),
+                )),
+                covey_proto::PluginAction::Prompt(prompt) => {
+                    Some(Action::Prompt(crate::from_proto::prompt(prompt, plugin)))
                 }
-            }),
+                covey_proto::PluginAction::Progress(covey_proto::Progress {
+                    fraction,
+                    message,
+                }) => Some(Action::Progress(
+                    Task {
+                        plugin: plugin.clone(),
+                        request_id: response.request_id,
+                    },
+                    Progress { fraction, message },
+                )),
+                covey_proto::PluginAction::EndProgress => Some(Action::EndProgress(Task {
+                    plugin: plugin.clone(),
+                    request_id: response.request_id,
+                })),
+                covey_proto::PluginAction::PushView(covey_proto::View { id, title }) => {
+                    let view = View {
+                        plugin: plugin.clone(),
+                        id,
+                        title,
+                    };
+                    self.views.push(view.clone());
+                    Some(Action::PushView(view))
+                }
+                // Opened by the host directly, frontends don't need to do anything.
+                covey_proto::PluginAction::OpenUrl(url) => {
+                    self.opener.open_url(&url);
+                    None
+                }
+                covey_proto::PluginAction::OpenPath(path) => {
+                    self.opener.open_path(&path);
+                    None
+                }
+            },
+        }
+    }
+
+    fn set_list(
+        &mut self,
+        plugin: &Plugin,
+        request_id: covey_proto::RequestId,
+        list: covey_proto::List,
+        is_complete: bool,
+    ) -> Option<Action> {
+        // Check if the latest received id < new id. If so, send the action.
+        // Otherwise, this response is outdated and we should not update the list.
+        let new = request_id.0;
+        if self.latest_received_query_request_id < new {
+            self.latest_received_query_request_id = new;
+            Some(Action::SetList(crate::from_proto::list(
+                list,
+                plugin,
+                request_id,
+                is_complete,
+            )))
+        } else {
+            tracing::trace!("ignoring list response due to outdated request id");
+            None
         }
     }
 }
@@ -306,8 +631,9 @@ fn load_plugins_from_config(
     config: &GlobalConfig,
     messages: &mpsc::UnboundedSender<Message>,
 ) -> KeyedList<Plugin> {
+    let theme = theme(&config.style);
     KeyedList::new_lossy(config.plugins.iter().filter_map(|plugin_entry| {
-        match Plugin::new_read_manifest(plugin_entry.clone(), messages.clone()) {
+        match Plugin::new_read_manifest(plugin_entry.clone(), theme.clone(), messages.clone()) {
             Ok(plugin) => {
                 debug!("found plugin {plugin:?}");
                 Some(plugin)
@@ -320,6 +646,70 @@ fn load_plugins_from_config(
     }))
 }
 
+/// Moves the running process of `old` to `new` if it doesn't need to be
+/// restarted.
+///
+/// Changed settings are sent to the process if the plugin supports
+/// [`covey_proto::Feature::Reconfigure`]. Otherwise, or if anything else that
+/// is sent when the process starts has changed, `new` starts a new process
+/// when it is first used.
+fn reuse_process(old: &Plugin, new: &Plugin) {
+    if old.manifest() != new.manifest() || old.theme() != new.theme() {
+        return;
+    }
+
+    let old_settings = &old.config_entry().settings;
+    let new_settings = &new.config_entry().settings;
+    if old_settings == new_settings {
+        new.adopt_process(old);
+        return;
+    }
+    if !old.supports(covey_proto::Feature::Reconfigure) {
+        return;
+    }
+
+    let (changed, removed) = settings_diff(old_settings, new_settings);
+    if new.adopt_process(old) {
+        debug!("reconfiguring plugin {}", new.id());
+        new.configure(changed, removed);
+    }
+}
+
+/// Settings that were added or changed in `new`, and the keys of settings
+/// that were removed.
+fn settings_diff(
+    old: &serde_json::Map<String, serde_json::Value>,
+    new: &serde_json::Map<String, serde_json::Value>,
+) -> (serde_json::Map<String, serde_json::Value>, Vec<String>) {
+    let changed = new
+        .iter()
+        .filter(|(key, value)| old.get(*key) != Some(value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let removed = old
+        .keys()
+        .filter(|key| !new.contains_key(*key))
+        .cloned()
+        .collect();
+    (changed, removed)
+}
+
+/// Stops the processes of plugins that have been replaced, without blocking
+/// while they shut down.
+fn shutdown_in_background(plugins: Vec<Plugin>) {
+    thread::spawn(move || crate::plugin::shutdown_processes(&plugins));
+}
+
+/// Colors sent to plugins when they start.
+fn theme(style: &UserStyle) -> covey_proto::Theme {
+    covey_proto::Theme {
+        background: style.bg_color(),
+        text: style.text_color(),
+        weak_text: style.weak_text_color(),
+        accent: style.match_highlight_color(),
+    }
+}
+
 /// Finds extra plugins from the plugin directory and inserts it into the
 /// config.
 fn find_and_insert_plugins_from_fs(config: &mut GlobalConfig) {
@@ -341,33 +731,75 @@ fn find_and_insert_plugins_from_fs(config: &mut GlobalConfig) {
     }));
 }
 
-/// Additional functions that need host internals but are exposed elsewhere.
-impl Host {
-    pub(crate) fn query_request_id_is_latest(&self, id: covey_proto::RequestId) -> bool {
+/// Request ID of the latest query sent to a plugin.
+#[derive(Clone, Default)]
+struct LatestQuery(Arc<AtomicU64>);
+
+impl LatestQuery {
+    /// Sets the latest request ID, returning the previous one.
+    fn replace(&self, id: covey_proto::RequestId) -> covey_proto::RequestId {
+        covey_proto::RequestId(self.0.swap(id.0, Ordering::Relaxed))
+    }
+
+    fn is(&self, id: covey_proto::RequestId) -> bool {
+        let latest = self.0.load(Ordering::Relaxed);
         debug_assert!(
-            self.latest_sent_query_request_id.0 >= id.0,
-            "found {id:?} when latest should be {:?}",
-            self.latest_sent_query_request_id
+            latest >= id.0,
+            "found {id:?} when latest should be {latest}"
         );
 
-        self.latest_sent_query_request_id.0 == id.0
+        latest == id.0
+    }
+}
+
+/// Additional functions that need host internals but are exposed elsewhere.
+impl Host {
+    pub(crate) fn query_request_id_is_latest(&self, id: covey_proto::RequestId) -> bool {
+        self.latest_sent_query.is(id)
     }
 
     pub(crate) fn resolve_icon(&self, icon: &Icon) -> Result<ResolvedIcon, ResolveIconError> {
-        match &icon.0 {
-            covey_proto::ListItemIcon::Text(text) => Ok(ResolvedIcon::Text(text.clone())),
-            covey_proto::ListItemIcon::Name(name) => {
-                match self.icon_cache.get_or_insert_with(name.clone()) {
-                    Some(Some(resolved)) => Ok(ResolvedIcon::File(resolved)),
-                    Some(None) => Err(ResolveIconError::NotFound),
+        self.resolve_proto_icon(&icon.0)
+    }
+
+    fn resolve_proto_icon(&self, icon: &IconKind) -> Result<ResolvedIcon, ResolveIconError> {
+        match icon {
+            IconKind::Text(text) => Ok(ResolvedIcon::Text(text.clone())),
+            IconKind::Name(name) => match self.icon_cache.get_or_insert_with(name.clone()) {
+                Some(Some(resolved)) => Ok(ResolvedIcon::File(resolved)),
+                Some(None) => Err(ResolveIconError::NotFound),
+                None => Err(ResolveIconError::Loading),
+            },
+            IconKind::File(path) => {
+                if !path.is_absolute() {
+                    return Err(ResolveIconError::NotFound);
+                }
+                match self.icon_file_cache.get_or_insert_with(path.clone()) {
+                    Some(true) => Ok(ResolvedIcon::File(path.clone())),
+                    Some(false) => Err(ResolveIconError::NotFound),
                     None => Err(ResolveIconError::Loading),
                 }
             }
+            IconKind::Data { uri, bytes } => Ok(ResolvedIcon::Bytes {
+                uri: uri.clone(),
+                bytes: Arc::clone(bytes),
+            }),
+            IconKind::Fallback(icons) => {
+                // Wait for earlier icons to load instead of showing a later
+                // icon then switching.
+                for icon in icons {
+                    match self.resolve_proto_icon(icon) {
+                        Err(ResolveIconError::NotFound) => {}
+                        result => return result,
+                    }
+                }
+                Err(ResolveIconError::NotFound)
+            }
         }
     }
 }
 
-/// Automatically kills plugin processes after a period of time if they haven't
+/// Automatically stops plugin processes after a period of time if they haven't
 /// been queried/activated.
 ///
 /// Never kills the most recently queried plugin to avoid the user trying to
@@ -378,7 +810,7 @@ struct PluginProcessGc {
 }
 
 impl PluginProcessGc {
-    /// Kills plugin processes after _at least_ `timeout` has passed.
+    /// Stops plugin processes after _at least_ `timeout` has passed.
     ///
     /// The exact time that the processes are killed is not precise and may be a
     /// while after `timeout`.
@@ -402,24 +834,30 @@ impl PluginProcessGc {
                     break;
                 }
 
-                let mut last_touched_times = last_touched_times.lock().unwrap();
-                let Some(most_recent_query) = last_touched_times.values().max().copied() else {
-                    // empty map, don't need to do anything
-                    continue;
-                };
-
-                last_touched_times.retain(|plugin, query_time| match plugin.upgrade() {
-                    Some(plugin) => {
-                        if query_time.elapsed() > timeout && *query_time != most_recent_query {
-                            plugin.kill_process();
-                            false
-                        } else {
-                            true
+                let mut expired = Vec::new();
+                {
+                    let mut last_touched_times = last_touched_times.lock().unwrap();
+                    let Some(most_recent_query) = last_touched_times.values().max().copied() else {
+                        // empty map, don't need to do anything
+                        continue;
+                    };
+
+                    last_touched_times.retain(|plugin, query_time| match plugin.upgrade() {
+                        Some(plugin) => {
+                            if query_time.elapsed() > timeout && *query_time != most_recent_query {
+                                expired.push(plugin);
+                                false
+                            } else {
+                                true
+                            }
                         }
-                    }
-                    // plugin is already gone
-                    None => false,
-                });
+                        // plugin is already gone
+                        None => false,
+                    });
+                }
+
+                // Shutting down can take a while, don't block `touch` meanwhile.
+                crate::plugin::shutdown_processes(&expired);
             }
         });
 
@@ -464,3 +902,34 @@ fn find_system_icon(name: &str, icon_themes: &[String]) -> Option<PathBuf> {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::settings_diff;
+
+    fn settings(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        let serde_json::Value::Object(map) = value else {
+            panic!("settings should be an object");
+        };
+        map
+    }
+
+    #[test]
+    fn diff_changed_and_removed_settings() {
+        let old = settings(json!({ "kept": 1, "changed": "a", "removed": true }));
+        let new = settings(json!({ "kept": 1, "changed": "b", "added": [1, 2] }));
+
+        let (changed, removed) = settings_diff(&old, &new);
+        assert_eq!(
+            changed,
+            settings(json!({ "changed": "b", "added": [1, 2] }))
+        );
+        assert_eq!(removed, ["removed"]);
+
+        let (changed, removed) = settings_diff(&new, &new);
+        assert!(changed.is_empty());
+        assert!(removed.is_empty());
+    }
+}
@@ -2,15 +2,20 @@ mod cache;
 mod event;
 mod from_proto;
 mod host;
+mod opener;
 mod plugin;
+mod storage;
+mod view;
 
 use std::{path::PathBuf, sync::LazyLock};
 
 pub use covey_schema;
 pub use event::{
-    Action, ActivationTarget, Icon, Input, List, ListItem, ResolveIconError, ResolvedIcon,
+    Action, ActivationTarget, ClipboardContent, Icon, Input, List, ListExtension, ListItem,
+    Notification, Preview, Progress, Prompt, PromptAnswer, PromptKind, ResolveIconError,
+    ResolvedIcon, Severity, Tag, Task, View,
 };
-pub use host::{ActionReceiver, Host, channel};
+pub use host::{ActionReceiver, Host, QueryOptions, channel};
 pub use plugin::{Plugin, PluginWeak};
 
 pub static CONFIG_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
@@ -0,0 +1,241 @@
+//! Opens URLs and paths requested by plugins.
+
+use std::{
+    ffi::{OsStr, OsString},
+    path::Path,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::{Context as _, Result, bail};
+use covey_schema::config::OpenerSettings;
+use futures::channel::mpsc;
+
+use crate::{Action, event::Message};
+
+/// Runs the commands configured in [`OpenerSettings`].
+///
+/// Shared between the host and the action receiver, so that settings can be
+/// changed on reload.
+#[derive(Clone)]
+pub(crate) struct Opener {
+    settings: Arc<Mutex<OpenerSettings>>,
+    messages: mpsc::UnboundedSender<Message>,
+}
+
+impl Opener {
+    pub(crate) fn new(settings: OpenerSettings, messages: mpsc::UnboundedSender<Message>) -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(settings)),
+            messages,
+        }
+    }
+
+    pub(crate) fn set_settings(&self, settings: OpenerSettings) {
+        *self.settings.lock().unwrap() = settings;
+    }
+
+    /// Opens a URL with the handler of its scheme.
+    ///
+    /// Errors are sent as a [`Action::DisplayError`].
+    pub(crate) fn open_url(&self, url: &str) {
+        let command = url_command(&self.settings.lock().unwrap(), url).to_vec();
+
+        self.spawn(&command, url.as_ref(), format!("Failed to open URL {url}"));
+    }
+
+    /// Opens a path with the handler of its MIME type.
+    ///
+    /// Errors are sent as a [`Action::DisplayError`].
+    pub(crate) fn open_path(&self, path: &Path) {
+        let mime_type = if path.is_dir() {
+            Some(String::from("inode/directory"))
+        } else {
+            mime_guess2::from_path(path)
+                .first()
+                .map(|mime| mime.essence_str().to_owned())
+        };
+        let command =
+            mime_type_command(&self.settings.lock().unwrap(), mime_type.as_deref()).to_vec();
+
+        self.spawn(
+            &command,
+            path.as_os_str(),
+            format!("Failed to open {}", path.display()),
+        );
+    }
+
+    fn spawn(&self, command: &[String], target: &OsStr, error_title: String) {
+        let mut child = match build_command(command, target).and_then(|mut command| {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .with_context(|| format!("failed to run {}", command.get_program().display()))
+        }) {
+            Ok(child) => child,
+            Err(e) => {
+                self.send_error(error_title, format!("{e:#}"));
+                return;
+            }
+        };
+
+        // Wait in another thread to report the exit status and avoid leaving
+        // a zombie process behind.
+        let this = self.clone();
+        thread::spawn(move || match child.wait() {
+            Ok(status) if status.success() => {}
+            Ok(status) => this.send_error(error_title, format!("opener exited with {status}")),
+            Err(e) => this.send_error(error_title, format!("{e:#}")),
+        });
+    }
+
+    fn send_error(&self, title: String, description: String) {
+        let _: Result<_, _> = self
+            .messages
+            .unbounded_send(Message::Action(Action::DisplayError(title, description)));
+    }
+}
+
+/// The command for the URL's scheme, or the default command.
+fn url_command<'a>(settings: &'a OpenerSettings, url: &str) -> &'a [String] {
+    url_scheme(url)
+        .and_then(|scheme| settings.schemes.get(&scheme.to_ascii_lowercase()))
+        .unwrap_or(&settings.default)
+}
+
+/// The command for the MIME type, then its wildcard like `text/*`, or the
+/// default command.
+fn mime_type_command<'a>(settings: &'a OpenerSettings, mime_type: Option<&str>) -> &'a [String] {
+    mime_type
+        .and_then(|mime_type| {
+            let wildcard = format!("{}/*", mime_type.split('/').next()?);
+            settings
+                .mime_types
+                .get(mime_type)
+                .or_else(|| settings.mime_types.get(&wildcard))
+        })
+        .unwrap_or(&settings.default)
+}
+
+/// Replaces `{}` in the arguments with the target, or appends the target if
+/// there is no `{}`.
+fn build_command(command: &[String], target: &OsStr) -> Result<Command> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("opener command is empty");
+    };
+
+    let mut cmd = Command::new(program);
+    let mut has_placeholder = false;
+    for arg in args {
+        if arg.contains("{}") {
+            has_placeholder = true;
+            let mut replaced = OsString::new();
+            for (i, part) in arg.split("{}").enumerate() {
+                if i > 0 {
+                    replaced.push(target);
+                }
+                replaced.push(part);
+            }
+            cmd.arg(replaced);
+        } else {
+            cmd.arg(arg);
+        }
+    }
+    if !has_placeholder {
+        cmd.arg(target);
+    }
+
+    Ok(cmd)
+}
+
+/// Returns the scheme of a URL, if it has one.
+fn url_scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    (chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+    .then_some(scheme)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use covey_schema::config::OpenerSettings;
+
+    use super::{build_command, mime_type_command, url_command, url_scheme};
+
+    fn command(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_owned()).collect()
+    }
+
+    fn settings() -> OpenerSettings {
+        OpenerSettings {
+            default: command(&["xdg-open"]),
+            schemes: [("mailto".to_owned(), command(&["thunderbird"]))].into(),
+            mime_types: [
+                ("text/*".to_owned(), command(&["gedit"])),
+                ("text/markdown".to_owned(), command(&["typora"])),
+            ]
+            .into(),
+        }
+    }
+
+    #[test]
+    fn schemes() {
+        assert_eq!(url_scheme("https://example.com"), Some("https"));
+        assert_eq!(url_scheme("git+ssh://host/repo"), Some("git+ssh"));
+        assert_eq!(url_scheme("/no/scheme"), None);
+        assert_eq!(url_scheme("1http://example.com"), None);
+
+        let settings = settings();
+        assert_eq!(
+            url_command(&settings, "MAILTO:me@example.com"),
+            ["thunderbird"]
+        );
+        assert_eq!(url_command(&settings, "https://example.com"), ["xdg-open"]);
+        assert_eq!(url_command(&settings, "example.com"), ["xdg-open"]);
+    }
+
+    #[test]
+    fn mime_types() {
+        let settings = settings();
+        assert_eq!(
+            mime_type_command(&settings, Some("text/markdown")),
+            ["typora"]
+        );
+        assert_eq!(mime_type_command(&settings, Some("text/plain")), ["gedit"]);
+        assert_eq!(
+            mime_type_command(&settings, Some("image/png")),
+            ["xdg-open"]
+        );
+        assert_eq!(mime_type_command(&settings, None), ["xdg-open"]);
+    }
+
+    #[test]
+    fn placeholders() {
+        let target = OsStr::new("/tmp/a b.txt");
+        let args = |command: &std::process::Command| {
+            command
+                .get_args()
+                .map(|arg| arg.to_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let cmd = build_command(&command(&["code", "--goto", "{}:1"]), target).unwrap();
+        assert_eq!(cmd.get_program(), "code");
+        assert_eq!(args(&cmd), ["--goto", "/tmp/a b.txt:1"]);
+
+        let cmd = build_command(&command(&["diff", "{}", "{}.bak"]), target).unwrap();
+        assert_eq!(args(&cmd), ["/tmp/a b.txt", "/tmp/a b.txt.bak"]);
+
+        // Appended if there is no placeholder.
+        let cmd = build_command(&command(&["xdg-open"]), target).unwrap();
+        assert_eq!(args(&cmd), ["/tmp/a b.txt"]);
+
+        assert!(build_command(&[], target).is_err());
+    }
+}
@@ -1,10 +1,11 @@
 use core::fmt;
 use std::{
     hash::Hash,
-    io::{self, BufRead as _, BufReader, Write as _},
+    io::{self, BufRead, BufReader, Write},
     path::{Path, PathBuf},
-    process::{Child, ChildStdin, Command, Stdio},
+    process::{Child, Command, Stdio},
     sync::{Arc, Mutex, Weak, atomic::AtomicU32},
+    time::{Duration, Instant},
 };
 
 use covey_schema::{
@@ -15,8 +16,12 @@ use covey_schema::{
     manifest::PluginManifest,
 };
 use futures::channel::mpsc;
+use interprocess::local_socket::{
+    GenericNamespaced, Listener, ListenerOptions, Stream, ToNsName as _,
+    traits::{Listener as _, Stream as _},
+};
 
-use crate::{DATA_DIR, event::Message};
+use crate::{Action, DATA_DIR, event::Message};
 
 /// An integer to distinguish between multiple constructions of the same plugin
 /// ID. Should not use pointer equality as an address may be reused when
@@ -41,23 +46,26 @@ pub struct Plugin {
 impl Plugin {
     pub(crate) fn new_read_manifest(
         entry: PluginEntry,
+        theme: covey_proto::Theme,
         messages: mpsc::UnboundedSender<Message>,
     ) -> anyhow::Result<Self> {
         let toml = std::fs::read_to_string(manifest_path(entry.id.as_str()))?;
         let manifest: PluginManifest = toml::from_str(&toml)?;
 
-        Ok(Self::new(entry, manifest, messages))
+        Ok(Self::new(entry, manifest, theme, messages))
     }
 
     pub(crate) fn new(
         entry: PluginEntry,
         manifest: PluginManifest,
+        theme: covey_proto::Theme,
         messages: mpsc::UnboundedSender<Message>,
     ) -> Self {
         Self {
             inner: Arc::new(PluginInner {
                 manifest,
                 entry,
+                theme,
                 messages: Mutex::new(messages),
                 process: Mutex::new(None),
             }),
@@ -65,11 +73,6 @@ impl Plugin {
         }
     }
 
-    pub(crate) fn kill_process(&self) {
-        // Dropping the ActiveProcess kills the process.
-        *self.inner.process.lock().unwrap() = None;
-    }
-
     pub fn id(&self) -> &PluginId {
         &self.inner.entry.id
     }
@@ -129,8 +132,23 @@ impl Plugin {
         &self.inner.manifest
     }
 
-    pub(crate) fn query(&self, id: covey_proto::RequestId, text: String) {
-        self.send_request_or_display_error(&covey_proto::Request::query(id, text))
+    pub(crate) fn theme(&self) -> &covey_proto::Theme {
+        &self.inner.theme
+    }
+
+    pub(crate) fn query(
+        &self,
+        id: covey_proto::RequestId,
+        text: String,
+        view: Option<covey_proto::ViewId>,
+        context: covey_proto::QueryContext,
+    ) {
+        self.send_request_or_display_error(&covey_proto::Request::query(
+            id,
+            text,
+            view,
+            Some(context),
+        ))
     }
     pub(crate) fn activate(
         &self,
@@ -143,12 +161,160 @@ impl Plugin {
         ))
     }
 
+    /// Requires [`covey_proto::Feature::BatchActivation`].
+    pub(crate) fn activate_many(
+        &self,
+        id: covey_proto::RequestId,
+        target_ids: Vec<covey_proto::ActivationTarget>,
+        command_id: CommandId,
+    ) {
+        self.send_request_or_display_error(&covey_proto::Request::activate_many(
+            id, target_ids, command_id,
+        ))
+    }
+
+    /// Whether the running plugin process supports a feature.
+    ///
+    /// Returns false if the process isn't running.
+    pub(crate) fn supports(&self, feature: covey_proto::Feature) -> bool {
+        self.inner
+            .process
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|process| process.supports(feature))
+    }
+
+    /// Cancels a request that has been sent to this plugin.
+    ///
+    /// Does nothing if the plugin process isn't running or doesn't support
+    /// cancellation.
+    pub(crate) fn cancel(&self, id: covey_proto::RequestId) {
+        let mut guard = self.inner.process.lock().unwrap();
+        if let Some(process) = &mut *guard
+            && process.supports(covey_proto::Feature::Cancellation)
+            && let Err(e) = process.send_request(&covey_proto::Request::cancel(id))
+        {
+            tracing::warn!(
+                "failed to cancel request {id:?} of plugin {}: {e:#}",
+                self.id()
+            );
+        }
+    }
+
+    /// Tells the plugin about a change in the host's state.
+    ///
+    /// Does nothing if the plugin process isn't running or doesn't support
+    /// lifecycle events.
+    pub(crate) fn send_lifecycle(&self, event: covey_proto::LifecycleEvent) {
+        let mut guard = self.inner.process.lock().unwrap();
+        if let Some(process) = &mut *guard
+            && process.supports(covey_proto::Feature::Lifecycle)
+            && let Err(e) = process.send_request(&covey_proto::Request::lifecycle(event))
+        {
+            tracing::warn!("failed to send {event:?} to plugin {}: {e:#}", self.id());
+        }
+    }
+
+    /// Tells the plugin that a view has been popped.
+    ///
+    /// Does nothing if the plugin process isn't running, as the view would
+    /// have been pushed by a different process.
+    pub(crate) fn close_view(&self, view_id: covey_proto::ViewId) {
+        let mut guard = self.inner.process.lock().unwrap();
+        if let Some(process) = &mut *guard
+            && let Err(e) = process.send_request(&covey_proto::Request::close_view(view_id))
+        {
+            tracing::warn!(
+                "failed to close view {view_id:?} of plugin {}: {e:#}",
+                self.id()
+            );
+        }
+    }
+
+    /// Answers a prompt sent by the plugin.
+    ///
+    /// Does nothing if the plugin process has stopped, as the prompt was
+    /// sent by a different process.
+    pub(crate) fn answer_prompt(
+        &self,
+        id: covey_proto::PromptId,
+        answer: Option<covey_proto::PromptAnswer>,
+    ) {
+        let mut guard = self.inner.process.lock().unwrap();
+        let Some(process) = &mut *guard else {
+            tracing::warn!(
+                "plugin {} stopped before prompt {id:?} was answered",
+                self.id()
+            );
+            return;
+        };
+
+        if let Err(e) = process.send_request(&covey_proto::Request::prompt_answer(id, answer)) {
+            tracing::warn!(
+                "failed to answer prompt {id:?} of plugin {}: {e:#}",
+                self.id()
+            );
+        }
+    }
+
+    /// Replies to a storage request of the plugin.
+    ///
+    /// Does nothing if the plugin process has stopped, as the request was
+    /// sent by a different process.
+    pub(crate) fn send_storage_result(
+        &self,
+        id: covey_proto::StorageId,
+        result: covey_proto::StorageResult,
+    ) {
+        let mut guard = self.inner.process.lock().unwrap();
+        if let Some(process) = &mut *guard
+            && let Err(e) = process.send_request(&covey_proto::Request::storage_result(id, result))
+        {
+            tracing::warn!(
+                "failed to reply to storage request {id:?} of plugin {}: {e:#}",
+                self.id()
+            );
+        }
+    }
+
+    /// Takes over the running process of `old`, which should be an older
+    /// construction of the same plugin.
+    ///
+    /// Returns false if `old` has no running process.
+    pub(crate) fn adopt_process(&self, old: &Plugin) -> bool {
+        let Some(process) = old.inner.process.lock().unwrap().take() else {
+            return false;
+        };
+        *process.owner.0.lock().unwrap() = self.downgrade();
+        *self.inner.process.lock().unwrap() = Some(process);
+        true
+    }
+
+    /// Sends changed settings to the running process.
+    ///
+    /// Requires [`covey_proto::Feature::Reconfigure`].
+    pub(crate) fn configure(
+        &self,
+        changed: serde_json::Map<String, serde_json::Value>,
+        removed: Vec<String>,
+    ) {
+        self.send_request_or_display_error(&covey_proto::Request::configure(changed, removed));
+    }
+
     fn start_process(&self) -> io::Result<ActiveProcess> {
         let bin_path = self.binary_path();
+        let initialize = covey_proto::Initialize {
+            settings: self.config_entry().settings.clone(),
+            covey_version: String::from(env!("CARGO_PKG_VERSION")),
+            data_dir: self.data_directory_path(),
+            locale: system_locale(),
+            theme: self.inner.theme.clone(),
+        };
         ActiveProcess::new(
             self.downgrade(),
             &bin_path,
-            &self.config_entry().settings,
+            initialize,
             self.inner.messages.lock().unwrap().clone(),
         )
     }
@@ -162,6 +328,8 @@ impl Plugin {
             Some(process) => {
                 match process.send_request(request) {
                     Ok(()) => Ok(()),
+                    // Refused by the handshake, restarting won't change anything.
+                    Err(e) if e.kind() == io::ErrorKind::Unsupported => Err(e),
                     Err(e) => {
                         // TODO: remove this log, only restart if the right error kind is reached
                         tracing::warn!("failed to write request: {e:#}");
@@ -173,7 +341,16 @@ impl Plugin {
                         // plugin, so this would only happen if something went wrong with the
                         // plugin.
                         match &request.request {
-                            covey_proto::RequestBody::Activate(..) => Err(e),
+                            covey_proto::RequestBody::Hello(..)
+                            | covey_proto::RequestBody::Initialize(..)
+                            | covey_proto::RequestBody::Activate(..)
+                            | covey_proto::RequestBody::ActivateMany(..)
+                            | covey_proto::RequestBody::Cancel(..)
+                            | covey_proto::RequestBody::PromptAnswer(..)
+                            | covey_proto::RequestBody::CloseView(..)
+                            | covey_proto::RequestBody::Configure(..)
+                            | covey_proto::RequestBody::Lifecycle(..)
+                            | covey_proto::RequestBody::StorageResult(..) => Err(e),
                             covey_proto::RequestBody::Query(..) => {
                                 *process = self.start_process()?;
                                 process.send_request(request)?;
@@ -220,6 +397,57 @@ impl Plugin {
     }
 }
 
+#[cfg(test)]
+impl Plugin {
+    /// A plugin with a minimal manifest. Its process is never started unless
+    /// a request is sent.
+    pub(crate) fn for_test(entry: PluginEntry) -> Self {
+        let manifest = toml::from_str(r#"name = "Test""#).expect("manifest should be valid");
+        Self::new(
+            entry,
+            manifest,
+            covey_proto::Theme::default(),
+            mpsc::unbounded().0,
+        )
+    }
+}
+
+/// How long a plugin process has to exit after being asked to shut down.
+pub(crate) const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// Stops the processes of all `plugins` at once, killing any that haven't
+/// exited after [`SHUTDOWN_GRACE_PERIOD`].
+///
+/// Blocks until all processes have exited or been killed.
+///
+/// Processes that don't support [`covey_proto::Feature::Lifecycle`] are
+/// killed immediately.
+pub(crate) fn shutdown_processes<'a>(plugins: impl IntoIterator<Item = &'a Plugin>) {
+    let stopping: Vec<_> = plugins
+        .into_iter()
+        .filter_map(|plugin| plugin.inner.process.lock().unwrap().take())
+        .filter_map(|mut process| process.request_shutdown().then_some(process))
+        .collect();
+
+    let deadline = Instant::now() + SHUTDOWN_GRACE_PERIOD;
+    for process in stopping {
+        process.wait_until(deadline);
+    }
+}
+
+/// The user's language from the environment, like `en-AU`.
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            // Remove the encoding and modifier, like in `en_AU.UTF-8@euro`.
+            let language = value.split(['.', '@']).next()?;
+            (!matches!(language, "" | "C" | "POSIX")).then(|| language.replace('_', "-"))
+        })
+}
+
 fn data_directory_path(plugin_name: &str) -> PathBuf {
     DATA_DIR.join("plugins").join(plugin_name)
 }
@@ -349,6 +577,8 @@ impl Identify for PluginWeak {
 struct PluginInner {
     manifest: PluginManifest,
     entry: PluginEntry,
+    /// Sent to the plugin when it starts.
+    theme: covey_proto::Theme,
     messages: Mutex<mpsc::UnboundedSender<Message>>,
     process: Mutex<Option<ActiveProcess>>,
 }
@@ -361,7 +591,71 @@ impl Drop for PluginInner {
 
 struct ActiveProcess {
     process: Child,
-    child_stdin: ChildStdin,
+    /// Shared with the response reader, which connects it once it knows
+    /// whether the plugin is using the socket.
+    requests: Arc<Mutex<RequestWriter>>,
+    handshake: Arc<Mutex<Handshake>>,
+    /// The plugin that responses are forwarded from.
+    owner: Arc<ProcessOwner>,
+}
+
+/// Can be changed while the process is running, so that a process can be
+/// kept when the plugin is reconfigured.
+struct ProcessOwner(Mutex<PluginWeak>);
+
+impl ProcessOwner {
+    fn upgrade(&self) -> Option<Plugin> {
+        self.0.lock().unwrap().upgrade()
+    }
+
+    fn id(&self) -> PluginId {
+        self.0.lock().unwrap().id().clone()
+    }
+}
+
+/// Writes requests to the plugin's stdin, or the socket it connected to.
+#[derive(Default)]
+struct RequestWriter {
+    /// [`None`] until the plugin's first line says whether it connected to
+    /// the socket. Requests are buffered until then.
+    writer: Option<Box<dyn Write + Send>>,
+    buffer: Vec<u8>,
+}
+
+impl RequestWriter {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        match &mut self.writer {
+            Some(writer) => {
+                writer.write_all(line)?;
+                writer.flush()
+            }
+            None => {
+                self.buffer.extend_from_slice(line);
+                Ok(())
+            }
+        }
+    }
+
+    /// Sends the buffered requests to `writer`, which is used for all later
+    /// requests.
+    fn connect(&mut self, writer: Box<dyn Write + Send>) -> io::Result<()> {
+        let buffer = std::mem::take(&mut self.buffer);
+        // Set even if writing fails, so that later requests fail too and the
+        // process is restarted.
+        let writer = self.writer.insert(writer);
+        writer.write_all(&buffer)?;
+        writer.flush()
+    }
+}
+
+/// State of the handshake with a plugin process.
+enum Handshake {
+    /// Waiting for the plugin to reply to the hello.
+    Pending,
+    /// Contains the plugin's hello.
+    Accepted(covey_proto::Hello),
+    /// Contains a user-facing reason for refusing the plugin.
+    Refused(String),
 }
 
 impl ActiveProcess {
@@ -369,85 +663,324 @@ impl ActiveProcess {
     pub(super) fn new(
         plugin_weak: PluginWeak,
         bin_path: &Path,
-        initialization_settings: &serde_json::Map<String, serde_json::Value>,
+        initialize: covey_proto::Initialize,
         messages: mpsc::UnboundedSender<Message>,
     ) -> io::Result<Self> {
-        let initialization_settings = serde_json::to_string(initialization_settings)
-            .expect("plugin init settings should be serializable");
-
-        let mut process = Command::new(bin_path)
-            .arg(initialization_settings)
+        // Plugins that understand `COVEY_SOCKET` connect to it, leaving stdio
+        // free for their own output. Plugins that can't connect use stdin/out
+        // instead.
+        let listener = listen()
+            .inspect_err(|e| tracing::warn!("failed to create plugin socket: {e:#}"))
+            .ok();
+
+        // Settings are sent over the protocol instead of as an argument, as
+        // they may be too long or contain secrets that shouldn't be visible in
+        // `ps`.
+        let mut command = Command::new(bin_path);
+        command
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .stdin(Stdio::piped())
-            .spawn()?;
+            .stdin(Stdio::piped());
+        if let Some((_, name)) = &listener {
+            command.env(SOCKET_ENV_VAR, name);
+        }
+        let mut process = command.spawn()?;
 
         let stdout = process.stdout.take().expect("stdout should be captured");
         let stderr = process.stderr.take().expect("stderr should be captured");
         let stdin = process.stdin.take().expect("stdin should be captured");
         let stderr = BufReader::new(stderr);
         let stdout = BufReader::new(stdout);
+        let handshake = Arc::new(Mutex::new(Handshake::Pending));
+        let requests = Arc::new(Mutex::new(RequestWriter::default()));
+        // Everything logged about the process is in this span.
+        let span = tracing::info_span!("plugin", id = %plugin_weak.id());
+        let owner = Arc::new(ProcessOwner(Mutex::new(plugin_weak)));
+
+        forward_output(Arc::clone(&owner), span.clone(), stderr, "stderr");
 
-        // Forward stderr as logs.
+        // Forward responses to the messages channel.
+        // Any unrecognised lines will be forwarded as logs, but as a warning.
+        // Plugins using stdin/out should not be printing logs to stdout.
         std::thread::spawn({
-            let plugin_weak = plugin_weak.clone();
+            let handshake = Arc::clone(&handshake);
+            let requests = Arc::clone(&requests);
+            let plugin_weak = Arc::clone(&owner);
             move || {
-                let mut lines = stderr.lines();
+                let _span = span.enter();
+
+                // The plugin's first line says whether it connected to the
+                // socket. Reading it can take a while, which is why this is
+                // done here instead of when starting the process.
+                let mut stdout = stdout;
+                let mut first_line = String::new();
+                if let Err(e) = stdout.read_line(&mut first_line) {
+                    tracing::warn!("failed to read plugin stdout: {e:#}");
+                }
+                let connection = match listener {
+                    Some((listener, _))
+                        if first_line.trim_end() == covey_proto::SOCKET_CONNECTED =>
+                    {
+                        accept(&listener)
+                    }
+                    _ => None,
+                };
+                let (first_line, responses, writer): (
+                    Option<String>,
+                    Box<dyn BufRead + Send>,
+                    Box<dyn Write + Send>,
+                ) = match connection {
+                    Some(stream) => {
+                        tracing::debug!("plugin connected to socket");
+                        forward_output(Arc::clone(&plugin_weak), span.clone(), stdout, "stdout");
+                        let (recv, send) = stream.split();
+                        (None, Box::new(BufReader::new(recv)), Box::new(send))
+                    }
+                    None => {
+                        // The first line is already part of the protocol.
+                        let first_line =
+                            Some(first_line.trim_end().to_owned()).filter(|line| !line.is_empty());
+                        (first_line, Box::new(stdout), Box::new(stdin))
+                    }
+                };
+                if let Err(e) = requests.lock().unwrap().connect(writer) {
+                    tracing::warn!("failed to send requests to plugin: {e:#}");
+                }
+
+                let mut lines = first_line.into_iter().map(Ok).chain(responses.lines());
                 while let Some(Ok(line)) = lines.next()
                     && let Some(plugin) = plugin_weak.upgrade()
                 {
-                    tracing::info!("plugin {id}: {line}", id = plugin.id());
-                }
-
-                tracing::info!("stopped reading plugin {:?} stderr", plugin_weak.id());
-            }
-        });
+                    let Ok(response) = serde_json::from_str::<covey_proto::Response>(&line) else {
+                        tracing::warn!("plugin {id} (protocol): {line}", id = plugin.id());
+                        continue;
+                    };
+
+                    tracing::trace!("plugin {id} (protocol): {line}", id = plugin.id());
+                    if let covey_proto::ResponseBody::Log(record) = response.response {
+                        emit_log(record);
+                        continue;
+                    }
+                    if let covey_proto::ResponseBody::Storage(request) = response.response {
+                        crate::storage::spawn_request(plugin.downgrade(), request);
+                        continue;
+                    }
+                    if let covey_proto::ResponseBody::Hello(hello) = response.response {
+                        let mut handshake = handshake.lock().unwrap();
+                        *handshake = Handshake::from_plugin_hello(&plugin, &hello);
+                        if let Handshake::Refused(reason) = &*handshake {
+                            send_refusal(&messages, &plugin, reason.clone());
+                        }
+                        continue;
+                    }
 
-        // Forward child stdout to the messages channel.
-        // Any unrecognised lines will be forwarded as logs, but as a warning.
-        // Plugins should not be printing logs to stdout.
-        std::thread::spawn(move || {
-            let mut lines = stdout.lines();
-            while let Some(Ok(line)) = lines.next()
-                && let Some(plugin) = plugin_weak.upgrade()
-            {
-                let Ok(response) = serde_json::from_str::<covey_proto::Response>(&line) else {
-                    tracing::warn!("plugin {id} (stdout): {line}", id = plugin.id());
-                    continue;
-                };
+                    if let Handshake::Refused(_) = &*handshake.lock().unwrap() {
+                        continue;
+                    }
 
-                tracing::trace!("plugin {id} (stdout): {line}", id = plugin.id());
-                match messages.unbounded_send(Message::PluginResponse(plugin.clone(), response)) {
-                    Ok(()) => {}
-                    Err(e) => {
-                        tracing::error!(?plugin, "failed to send response through channel: {e:#}");
-                        return;
+                    match messages.unbounded_send(Message::PluginResponse(plugin.clone(), response))
+                    {
+                        Ok(()) => {}
+                        Err(e) => {
+                            tracing::error!(
+                                ?plugin,
+                                "failed to send response through channel: {e:#}"
+                            );
+                            return;
+                        }
                     }
                 }
-            }
 
-            tracing::info!("stopped reading plugin {:?} stdout", plugin_weak.id());
-        });
+                tracing::info!("stopped reading plugin {:?} responses", plugin_weak.id());
 
-        // Must work with child stdin directly instead of a channel so that attempted
-        // requests can fail if the child has been killed.
+                let mut handshake = handshake.lock().unwrap();
+                if let Handshake::Pending = &*handshake
+                    && let Some(plugin) = plugin_weak.upgrade()
+                {
+                    let reason = String::from(
+                        "The plugin stopped before replying to the protocol handshake. \
+                         It may have been built with an older version of covey-plugin.",
+                    );
+                    send_refusal(&messages, &plugin, reason.clone());
+                    *handshake = Handshake::Refused(reason);
+                }
+            }
+        });
 
-        Ok(Self {
+        // Once connected, requests are written to the process directly
+        // instead of through a channel, so that attempted requests can fail
+        // if the child has been killed.
+        let mut this = Self {
             process,
-            child_stdin: stdin,
-        })
+            requests,
+            handshake,
+            owner,
+        };
+        this.send_request(&covey_proto::Request::hello(covey_proto::Hello::current()))?;
+        this.send_request(&covey_proto::Request::initialize(initialize))?;
+        Ok(this)
     }
 
     /// Tries to send the request to the process. Does not retry on failure.
     pub(super) fn send_request(&mut self, request: &covey_proto::Request) -> io::Result<()> {
+        if let Handshake::Refused(reason) = &*self.handshake.lock().unwrap() {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, reason.clone()));
+        }
+
         let mut json = serde_json::to_string(request).expect("request should be serializable");
         json.push('\n');
-        self.child_stdin.write_all(json.as_bytes())?;
-        self.child_stdin.flush()?;
-        Ok(())
+        self.requests.lock().unwrap().write_line(json.as_bytes())
+    }
+
+    /// Asks the process to exit by itself.
+    ///
+    /// Returns false if it doesn't support this, in which case it should just
+    /// be killed.
+    fn request_shutdown(&mut self) -> bool {
+        if !self.supports(covey_proto::Feature::Lifecycle) {
+            return false;
+        }
+        let request = covey_proto::Request::lifecycle(covey_proto::LifecycleEvent::Shutdown);
+        self.send_request(&request).is_ok()
+    }
+
+    /// Waits for the process to exit, killing it if it is still running at
+    /// `deadline`.
+    fn wait_until(mut self, deadline: Instant) {
+        while Instant::now() < deadline {
+            match self.process.try_wait() {
+                Ok(Some(_)) => return,
+                Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+                Err(e) => {
+                    tracing::error!(
+                        "failed to check whether plugin {} has exited, killing it: {e:#}",
+                        self.owner.id()
+                    );
+                    return;
+                }
+            }
+        }
+        tracing::warn!("killing plugin {} after shutdown timeout", self.owner.id());
+        // Dropping kills the process.
+    }
+
+    /// Whether the plugin has completed the handshake and supports this
+    /// feature.
+    fn supports(&self, feature: covey_proto::Feature) -> bool {
+        match &*self.handshake.lock().unwrap() {
+            Handshake::Accepted(hello) => hello.supports(feature),
+            Handshake::Pending | Handshake::Refused(_) => false,
+        }
     }
 }
 
+impl Handshake {
+    fn from_plugin_hello(plugin: &Plugin, hello: &covey_proto::Hello) -> Self {
+        let host_hello = covey_proto::Hello::current();
+
+        if !host_hello.is_compatible_with(hello) {
+            return Self::Refused(format!(
+                "The plugin uses protocol version {}, but covey uses protocol version {}. \
+                 Rebuild the plugin with a compatible version of covey-plugin.",
+                hello.protocol_version, host_hello.protocol_version
+            ));
+        }
+
+        let unsupported: Vec<_> = host_hello.features.difference(&hello.features).collect();
+        if !unsupported.is_empty() {
+            tracing::info!(
+                "plugin {} does not support features {unsupported:?}, these will be disabled",
+                plugin.id()
+            );
+        }
+
+        Self::Accepted(hello.clone())
+    }
+}
+
+/// Logs a record sent by a plugin as if it was logged by covey.
+///
+/// Should be called in the plugin's span.
+fn emit_log(record: covey_proto::LogRecord) {
+    let covey_proto::LogRecord {
+        level,
+        target,
+        message,
+        fields,
+    } = record;
+    // Field names must be known at compile time, so all other fields are
+    // logged together.
+    let fields = serde_json::Value::Object(fields.into_iter().collect());
+
+    macro_rules! emit {
+        ($level:expr) => {
+            tracing::event!($level, plugin_target = %target, %fields, "{message}")
+        };
+    }
+    match level {
+        covey_proto::LogLevel::Trace => emit!(tracing::Level::TRACE),
+        covey_proto::LogLevel::Debug => emit!(tracing::Level::DEBUG),
+        covey_proto::LogLevel::Info => emit!(tracing::Level::INFO),
+        covey_proto::LogLevel::Warn => emit!(tracing::Level::WARN),
+        covey_proto::LogLevel::Error => emit!(tracing::Level::ERROR),
+    }
+}
+
+/// Environment variable with the name of the socket that the plugin should
+/// connect to.
+const SOCKET_ENV_VAR: &str = "COVEY_SOCKET";
+
+static NEXT_SOCKET_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Creates a socket for a new plugin process, returning it with its name.
+fn listen() -> io::Result<(Listener, String)> {
+    let name = format!(
+        "covey-{}-plugin-{}.sock",
+        std::process::id(),
+        NEXT_SOCKET_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    );
+    let listener = ListenerOptions::new()
+        .name(name.as_str().to_ns_name::<GenericNamespaced>()?)
+        .create_sync()?;
+    Ok((listener, name))
+}
+
+/// Accepts the connection of a plugin that said it connected to the socket.
+fn accept(listener: &Listener) -> Option<Stream> {
+    listener
+        .accept()
+        .inspect_err(|e| tracing::warn!("failed to accept plugin connection: {e:#}"))
+        .ok()
+}
+
+/// Forwards output of the plugin that isn't part of the protocol as logs.
+fn forward_output(
+    plugin_weak: Arc<ProcessOwner>,
+    span: tracing::Span,
+    output: impl BufRead + Send + 'static,
+    name: &'static str,
+) {
+    std::thread::spawn(move || {
+        let _span = span.enter();
+        let mut lines = output.lines();
+        while let Some(Ok(line)) = lines.next()
+            && plugin_weak.upgrade().is_some()
+        {
+            tracing::info!("({name}) {line}");
+        }
+
+        tracing::info!("stopped reading plugin {:?} {name}", plugin_weak.id());
+    });
+}
+
+fn send_refusal(messages: &mpsc::UnboundedSender<Message>, plugin: &Plugin, reason: String) {
+    tracing::error!("refused plugin {}: {reason}", plugin.id());
+    let _: Result<_, _> = messages.unbounded_send(Message::Action(Action::DisplayError(
+        format!("Plugin {} is incompatible", plugin.id()),
+        reason,
+    )));
+}
+
 impl Drop for ActiveProcess {
     fn drop(&mut self) {
         // This also stops the stdout/err forwarding threads as the readers are closed.
@@ -0,0 +1,269 @@
+//! Key/value storage that plugins access with
+//! [`covey_proto::ResponseBody::Storage`].
+
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{LazyLock, mpsc},
+    thread,
+};
+
+use anyhow::Result;
+use covey_proto::{StorageOperation, StorageRequest, StorageResult};
+use covey_schema::id::{PluginId, StringId as _};
+use redb::{Database, ReadableDatabase as _, TableDefinition, TableError};
+
+use crate::{DATA_DIR, plugin::PluginWeak};
+
+/// Runs storage requests on a separate thread, in the order they were
+/// received.
+///
+/// Writes can take a while to commit, and shouldn't stop the plugin's other
+/// responses from being read meanwhile.
+static WORKER: LazyLock<mpsc::Sender<(PluginWeak, StorageRequest)>> = LazyLock::new(|| {
+    let (tx, rx) = mpsc::channel::<(PluginWeak, StorageRequest)>();
+    thread::spawn(move || {
+        let storage = std::fs::create_dir_all(&*DATA_DIR)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| Storage::open(&DATA_DIR.join("storage.redb")))
+            .map_err(|e| format!("failed to open plugin storage: {e:#}"));
+
+        for (plugin, request) in rx {
+            // The request was sent by a process that has been stopped.
+            let Some(plugin) = plugin.upgrade() else {
+                continue;
+            };
+            let result = match &storage {
+                Ok(storage) => storage.handle(plugin.id(), request.operation),
+                Err(e) => StorageResult::Error(e.clone()),
+            };
+            plugin.send_storage_result(request.storage_id, result);
+        }
+    });
+    tx
+});
+
+/// Handles a storage request of the plugin without blocking, replying to the
+/// plugin once it's done.
+pub(crate) fn spawn_request(plugin: PluginWeak, request: StorageRequest) {
+    WORKER
+        .send((plugin, request))
+        .expect("storage worker should never stop");
+}
+
+/// Storage of all plugins, with a table for each plugin.
+///
+/// Values are stored as JSON.
+pub(crate) struct Storage {
+    database: Database,
+}
+
+impl Storage {
+    /// Opens the database at `path`, creating it if it doesn't exist.
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            database: Database::create(path)?,
+        })
+    }
+
+    /// Runs the operation in its own transaction.
+    pub(crate) fn handle(
+        &self,
+        plugin_id: &PluginId,
+        operation: StorageOperation,
+    ) -> StorageResult {
+        self.run(plugin_id, operation)
+            .unwrap_or_else(|e| StorageResult::Error(format!("{e:#}")))
+    }
+
+    fn run(&self, plugin_id: &PluginId, operation: StorageOperation) -> Result<StorageResult> {
+        let definition = TableDefinition::<&str, &str>::new(plugin_id.as_str());
+
+        match operation {
+            StorageOperation::Get { key } => {
+                let transaction = self.database.begin_read()?;
+                let table = match transaction.open_table(definition) {
+                    Ok(table) => table,
+                    // Nothing has been stored yet.
+                    Err(TableError::TableDoesNotExist(_)) => {
+                        return Ok(StorageResult::Value(None));
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                let value = table
+                    .get(key.as_str())?
+                    .map(|value| serde_json::from_str(value.value()))
+                    .transpose()?;
+                Ok(StorageResult::Value(value))
+            }
+            StorageOperation::Set { key, value } => {
+                let json = serde_json::to_string(&value)?;
+                let transaction = self.database.begin_write()?;
+                transaction
+                    .open_table(definition)?
+                    .insert(key.as_str(), json.as_str())?;
+                transaction.commit()?;
+                Ok(StorageResult::Done)
+            }
+            StorageOperation::Delete { key } => {
+                let transaction = self.database.begin_write()?;
+                transaction.open_table(definition)?.remove(key.as_str())?;
+                transaction.commit()?;
+                Ok(StorageResult::Done)
+            }
+            StorageOperation::List { prefix } => {
+                let transaction = self.database.begin_read()?;
+                let table = match transaction.open_table(definition) {
+                    Ok(table) => table,
+                    Err(TableError::TableDoesNotExist(_)) => {
+                        return Ok(StorageResult::Entries(BTreeMap::new()));
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                let mut entries = BTreeMap::new();
+                for entry in table.range(prefix.as_str()..)? {
+                    let (key, value) = entry?;
+                    if !key.value().starts_with(&prefix) {
+                        break;
+                    }
+                    entries.insert(key.value().to_owned(), serde_json::from_str(value.value())?);
+                }
+                Ok(StorageResult::Entries(entries))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use covey_proto::{StorageOperation, StorageResult};
+    use covey_schema::id::PluginId;
+    use serde_json::json;
+
+    use super::Storage;
+
+    fn open() -> (tempfile::TempDir, Storage) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(&dir.path().join("storage.redb")).unwrap();
+        (dir, storage)
+    }
+
+    fn get(storage: &Storage, plugin: &PluginId, key: &str) -> StorageResult {
+        storage.handle(
+            plugin,
+            StorageOperation::Get {
+                key: key.to_owned(),
+            },
+        )
+    }
+
+    fn set(storage: &Storage, plugin: &PluginId, key: &str, value: serde_json::Value) {
+        let result = storage.handle(
+            plugin,
+            StorageOperation::Set {
+                key: key.to_owned(),
+                value,
+            },
+        );
+        assert_eq!(result, StorageResult::Done);
+    }
+
+    #[test]
+    fn get_set_delete() {
+        let (_dir, storage) = open();
+        let plugin = PluginId::new("notes");
+
+        // The plugin's table doesn't exist yet.
+        assert_eq!(get(&storage, &plugin, "a"), StorageResult::Value(None));
+
+        set(&storage, &plugin, "a", json!({ "text": "hi" }));
+        assert_eq!(
+            get(&storage, &plugin, "a"),
+            StorageResult::Value(Some(json!({ "text": "hi" })))
+        );
+
+        set(&storage, &plugin, "a", json!(2));
+        assert_eq!(
+            get(&storage, &plugin, "a"),
+            StorageResult::Value(Some(json!(2)))
+        );
+
+        let result = storage.handle(
+            &plugin,
+            StorageOperation::Delete {
+                key: "a".to_owned(),
+            },
+        );
+        assert_eq!(result, StorageResult::Done);
+        assert_eq!(get(&storage, &plugin, "a"), StorageResult::Value(None));
+    }
+
+    #[test]
+    fn list_prefix() {
+        let (_dir, storage) = open();
+        let plugin = PluginId::new("bookmarks");
+        let list = |prefix: &str| {
+            storage.handle(
+                &plugin,
+                StorageOperation::List {
+                    prefix: prefix.to_owned(),
+                },
+            )
+        };
+
+        assert_eq!(list("links/"), StorageResult::Entries([].into()));
+
+        set(&storage, &plugin, "links/covey", json!(1));
+        set(&storage, &plugin, "links/rust", json!(2));
+        set(&storage, &plugin, "linksx/other", json!(3));
+        set(&storage, &plugin, "tags/rust", json!(4));
+
+        assert_eq!(
+            list("links/"),
+            StorageResult::Entries(
+                [
+                    ("links/covey".to_owned(), json!(1)),
+                    ("links/rust".to_owned(), json!(2)),
+                ]
+                .into()
+            )
+        );
+        assert_eq!(
+            list("links/r"),
+            StorageResult::Entries([("links/rust".to_owned(), json!(2))].into())
+        );
+        let StorageResult::Entries(all) = list("") else {
+            panic!("expected entries");
+        };
+        assert_eq!(all.len(), 4);
+    }
+
+    #[test]
+    fn plugins_are_isolated() {
+        let (_dir, storage) = open();
+        let first = PluginId::new("first");
+        let second = PluginId::new("second");
+
+        set(&storage, &first, "key", json!("first"));
+        set(&storage, &second, "key", json!("second"));
+
+        assert_eq!(
+            get(&storage, &first, "key"),
+            StorageResult::Value(Some(json!("first")))
+        );
+        assert_eq!(
+            get(&storage, &second, "key"),
+            StorageResult::Value(Some(json!("second")))
+        );
+        let list_second = storage.handle(
+            &second,
+            StorageOperation::List {
+                prefix: String::new(),
+            },
+        );
+        assert_eq!(
+            list_second,
+            StorageResult::Entries([("key".to_owned(), json!("second"))].into())
+        );
+    }
+}
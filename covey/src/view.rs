@@ -0,0 +1,120 @@
+//! Views pushed on top of a plugin's main list.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{Plugin, View};
+
+/// Stack of views that are currently open.
+///
+/// Shared between the host, which queries the top view, and the action
+/// receiver, which pushes new views.
+#[derive(Clone, Default)]
+pub(crate) struct ViewStack(Arc<Mutex<ViewStackInner>>);
+
+#[derive(Default)]
+struct ViewStackInner {
+    /// Each view with the input before it was pushed.
+    frames: Vec<(View, String)>,
+    /// Input of the latest query, restored when the next view is popped.
+    latest_query: String,
+}
+
+impl ViewStack {
+    pub(crate) fn set_latest_query(&self, query: &str) {
+        query.clone_into(&mut self.0.lock().unwrap().latest_query);
+    }
+
+    pub(crate) fn push(&self, view: View) {
+        let mut inner = self.0.lock().unwrap();
+        let input = std::mem::take(&mut inner.latest_query);
+        inner.frames.push((view, input));
+    }
+
+    /// Removes the top view, returning it with the input to restore.
+    pub(crate) fn pop(&self) -> Option<(View, String)> {
+        self.0.lock().unwrap().frames.pop()
+    }
+
+    pub(crate) fn top(&self) -> Option<View> {
+        let inner = self.0.lock().unwrap();
+        inner.frames.last().map(|(view, _)| view.clone())
+    }
+
+    /// Whether `plugin` owns the top view.
+    pub(crate) fn top_is_from(&self, plugin: &Plugin) -> bool {
+        let inner = self.0.lock().unwrap();
+        inner
+            .frames
+            .last()
+            .is_some_and(|(view, _)| view.plugin == *plugin)
+    }
+
+    pub(crate) fn views(&self) -> Vec<View> {
+        let inner = self.0.lock().unwrap();
+        inner.frames.iter().map(|(view, _)| view.clone()).collect()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.0.lock().unwrap().frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use covey_schema::{config::PluginEntry, id::PluginId};
+
+    use super::ViewStack;
+    use crate::{Plugin, View};
+
+    fn view(plugin: &Plugin, id: u64) -> View {
+        View {
+            plugin: plugin.clone(),
+            id: covey_proto::ViewId(id),
+            title: format!("view {id}"),
+        }
+    }
+
+    #[test]
+    fn pop_restores_input() {
+        let plugin = Plugin::for_test(PluginEntry::new(PluginId::new("files")));
+        let other = Plugin::for_test(PluginEntry::new(PluginId::new("other")));
+        let views = ViewStack::default();
+        assert!(views.top().is_none());
+
+        views.set_latest_query("f docs");
+        views.push(view(&plugin, 1));
+        views.set_latest_query("notes");
+        views.push(view(&plugin, 2));
+        // Typed in the top view, but never pushed.
+        views.set_latest_query("draft");
+
+        assert_eq!(views.views().len(), 2);
+        assert_eq!(views.top().unwrap().id, covey_proto::ViewId(2));
+        assert!(views.top_is_from(&plugin));
+        assert!(!views.top_is_from(&other));
+
+        let (popped, input) = views.pop().unwrap();
+        assert_eq!(popped.id, covey_proto::ViewId(2));
+        assert_eq!(input, "notes");
+
+        let (popped, input) = views.pop().unwrap();
+        assert_eq!(popped.id, covey_proto::ViewId(1));
+        assert_eq!(input, "f docs");
+
+        assert!(views.pop().is_none());
+        assert!(!views.top_is_from(&plugin));
+    }
+
+    #[test]
+    fn clear() {
+        let plugin = Plugin::for_test(PluginEntry::new(PluginId::new("files")));
+        let views = ViewStack::default();
+        views.push(view(&plugin, 1));
+        views.clone().push(view(&plugin, 2));
+
+        // Clones share the same stack.
+        assert_eq!(views.views().len(), 2);
+        views.clear();
+        assert!(views.views().is_empty());
+    }
+}